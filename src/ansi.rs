@@ -0,0 +1,296 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+const TAB_WIDTH: usize = 8;
+
+// turns SGR escape sequences into span styles on top of `base`; any other
+// escape sequence or control character is dropped rather than printed
+pub fn to_line(text: &str, base: Style) -> Line<'static> {
+    let (earlier, last) = final_state(text);
+    // a colour set before the redraw still applies to it
+    let (_, style) = styled_spans(earlier, base, base);
+    let (spans, _) = styled_spans(last, base, style);
+    Line::from(spans)
+}
+
+// the spans of `text` starting out in `style`, and the style it ends in
+fn styled_spans(text: &str, base: Style, mut style: Style) -> (Vec<Span<'static>>, Style) {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut column = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ESC => {}
+            '\t' => {
+                let width = TAB_WIDTH - column % TAB_WIDTH;
+                current.extend(std::iter::repeat_n(' ', width));
+                column += width;
+                continue;
+            }
+            c if c.is_control() => continue,
+            c => {
+                current.push(c);
+                column += 1;
+                continue;
+            }
+        }
+
+        match chars.next() {
+            // CSI: parameter and intermediate bytes up to the final byte
+            Some('[') => {
+                let mut params = String::new();
+                let mut final_byte = None;
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        final_byte = Some(c);
+                        break;
+                    }
+                    params.push(c);
+                }
+
+                let private = params.starts_with(['?', '<', '=', '>']);
+                if final_byte == Some('m') && !private {
+                    if !current.is_empty() {
+                        spans.push(Span::styled(std::mem::take(&mut current), style));
+                    }
+                    style = apply_sgr(style, base, &params);
+                }
+            }
+            // OSC (titles, hyperlinks) and the other string sequences run
+            // until BEL or ST; hyperlink text itself sits outside them
+            Some(']' | 'P' | 'X' | '^' | '_') => {
+                while let Some(c) = chars.next() {
+                    if c == BEL || (c == ESC && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // `ESC ( B` and friends: intermediate bytes, then one final byte
+            Some(' '..='/') => {
+                while chars.next_if(|c| (' '..='/').contains(c)).is_some() {}
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+
+    if !current.is_empty() {
+        spans.push(Span::styled(current, style));
+    }
+    (spans, style)
+}
+
+// progress bars redraw themselves after a carriage return; only the last
+// drawing is kept, the ones before it are returned for their styles
+fn final_state(text: &str) -> (&str, &str) {
+    let text = text.trim_end_matches('\r');
+    match text.rfind('\r') {
+        Some(i) => (&text[..i], &text[i + 1..]),
+        None => ("", text),
+    }
+}
+
+fn apply_sgr(mut style: Style, base: Style, params: &str) -> Style {
+    let mut groups = params.split(';');
+    while let Some(group) = groups.next() {
+        let mut parts = group.split(':');
+        // an empty parameter means 0, so `ESC[m` resets like `ESC[0m`
+        let code = match parts.next().unwrap_or_default() {
+            "" => 0,
+            code => match code.parse::<u16>() {
+                Ok(code) => code,
+                Err(_) => continue,
+            },
+        };
+
+        match code {
+            0 => style = base,
+            1 => style = style.add_modifier(Modifier::BOLD),
+            2 => style = style.add_modifier(Modifier::DIM),
+            3 => style = style.add_modifier(Modifier::ITALIC),
+            // `4:0` turns underline off; the other `4:n` styles are all shown
+            // as a plain underline
+            4 if parts.next() == Some("0") => style = style.remove_modifier(Modifier::UNDERLINED),
+            4 | 21 => style = style.add_modifier(Modifier::UNDERLINED),
+            5 | 6 => style = style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style = style.add_modifier(Modifier::REVERSED),
+            8 => style = style.add_modifier(Modifier::HIDDEN),
+            9 => style = style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style = style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style = style.remove_modifier(Modifier::ITALIC),
+            24 => style = style.remove_modifier(Modifier::UNDERLINED),
+            25 => style = style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style = style.remove_modifier(Modifier::REVERSED),
+            28 => style = style.remove_modifier(Modifier::HIDDEN),
+            29 => style = style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg = Some(basic_color(code - 30)),
+            39 => style.fg = base.fg,
+            40..=47 => style.bg = Some(basic_color(code - 40)),
+            49 => style.bg = base.bg,
+            90..=97 => style.fg = Some(bright_color(code - 90)),
+            100..=107 => style.bg = Some(bright_color(code - 100)),
+            38 | 48 | 58 => {
+                let args = if group.contains(':') {
+                    colon_color_args(parts)
+                } else {
+                    semicolon_color_args(&mut groups)
+                };
+                let color = extended_color(&args);
+                match code {
+                    38 => style.fg = color.or(style.fg),
+                    48 => style.bg = color.or(style.bg),
+                    // underline colors are parsed only to skip their arguments
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    style
+}
+
+// `38;5;n` and `38;2;r;g;b` take their arguments from the following groups
+fn semicolon_color_args<'a>(groups: &mut impl Iterator<Item = &'a str>) -> Vec<u16> {
+    let mut number = || groups.next().and_then(|g| g.parse::<u16>().ok());
+    match number() {
+        Some(5) => [Some(5), number()].into_iter().flatten().collect(),
+        Some(2) => [Some(2), number(), number(), number()]
+            .into_iter()
+            .flatten()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// `38:5:n`, `38:2:r:g:b` and `38:2:<colorspace>:r:g:b`
+fn colon_color_args<'a>(parts: impl Iterator<Item = &'a str>) -> Vec<u16> {
+    let parts: Vec<&str> = parts.collect();
+    let parts = match parts.as_slice() {
+        ["2", _, r, g, b] => vec!["2", r, g, b],
+        _ => parts,
+    };
+    parts.iter().filter_map(|p| p.parse().ok()).collect()
+}
+
+fn extended_color(args: &[u16]) -> Option<Color> {
+    let byte = |value: u16| u8::try_from(value).ok();
+    match *args {
+        [5, index] => byte(index).map(Color::Indexed),
+        [2, r, g, b] => Some(Color::Rgb(byte(r)?, byte(g)?, byte(b)?)),
+        _ => None,
+    }
+}
+
+fn basic_color(index: u16) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        _ => Color::Gray,
+    }
+}
+
+fn bright_color(index: u16) -> Color {
+    match index {
+        0 => Color::DarkGray,
+        1 => Color::LightRed,
+        2 => Color::LightGreen,
+        3 => Color::LightYellow,
+        4 => Color::LightBlue,
+        5 => Color::LightMagenta,
+        6 => Color::LightCyan,
+        _ => Color::White,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(text: &str) -> Vec<(String, Style)> {
+        to_line(text, Style::default())
+            .spans
+            .into_iter()
+            .map(|span| (span.content.into_owned(), span.style))
+            .collect()
+    }
+
+    #[test]
+    fn indexed_and_rgb_colors() {
+        assert_eq!(
+            spans("\x1b[38;5;208mwarn\x1b[0m ok"),
+            [
+                ("warn".to_string(), Style::default().fg(Color::Indexed(208))),
+                (" ok".to_string(), Style::default()),
+            ]
+        );
+        assert_eq!(
+            spans("\x1b[1;38;2;255;128;0mhot"),
+            [(
+                "hot".to_string(),
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(Color::Rgb(255, 128, 0))
+            )]
+        );
+        assert_eq!(
+            spans("\x1b[48:2::0:0:255mblue"),
+            [("blue".to_string(), Style::default().bg(Color::Rgb(0, 0, 255)))]
+        );
+    }
+
+    #[test]
+    fn out_of_range_colors_are_ignored() {
+        assert_eq!(
+            spans("\x1b[38;5;300mtext"),
+            [("text".to_string(), Style::default())]
+        );
+    }
+
+    #[test]
+    fn progress_lines_keep_the_last_drawing() {
+        assert_eq!(
+            spans(" 10%\r 50%\r100%\r"),
+            [("100%".to_string(), Style::default())]
+        );
+        assert_eq!(
+            spans("\x1b[32mdone\x1b[0m\r"),
+            [("done".to_string(), Style::default().fg(Color::Green))]
+        );
+    }
+
+    #[test]
+    fn progress_lines_keep_the_style_of_earlier_drawings() {
+        assert_eq!(
+            spans("\x1b[32m10%\r50%"),
+            [("50%".to_string(), Style::default().fg(Color::Green))]
+        );
+        assert_eq!(
+            spans("\x1b[1;32m10%\x1b[22m\r\x1b[4m50%"),
+            [(
+                "50%".to_string(),
+                Style::default()
+                    .fg(Color::Green)
+                    .remove_modifier(Modifier::BOLD | Modifier::DIM)
+                    .add_modifier(Modifier::UNDERLINED)
+            )]
+        );
+    }
+
+    #[test]
+    fn other_sequences_and_controls_are_dropped() {
+        assert_eq!(
+            spans("\x1b]0;title\x07a\x1b[2Kb\x1b(Bc\x08"),
+            [("abc".to_string(), Style::default())]
+        );
+        assert_eq!(spans("a\tb"), [("a       b".to_string(), Style::default())]);
+    }
+}
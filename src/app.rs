@@ -1,48 +1,275 @@
-use crate::parser::{Task, Parser, FileType};
-use anyhow::Result;
-use std::collections::HashSet;
+use crate::ansi;
+use crate::config::{format_duration, Config, Timeouts};
+use crate::executor::{ExecutionMode, GracePeriods, Invocation, OutputLine, RunEvent, RunHandle, RunStatus, Stream};
+use crate::history::{self, DurationStats, History, HistoryEntry, RunLog};
+use crate::keymap::{Keymap, Sequence};
+use crate::theme::Theme;
+use crate::params::{Arguments, ParamForm};
+use crate::parser::{join_dependencies, make_reference, Task, Parser, FileType, Variable};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Local};
+use ratatui::style::Style;
+use ratatui::text::Line;
+use std::collections::{HashSet, VecDeque};
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// runs shown in the duration trend of the task details
+const SPARKLINE_RUNS: usize = 30;
+// a last run this much slower than the median is called out
+const SLOWDOWN_RATIO: f64 = 1.5;
+// output lines a run keeps in memory; older ones are only in its log
+pub const MAX_SHOWN_LINES: usize = 10_000;
 
 pub struct App {
     pub tasks: Vec<Task>,
+    pub variables: Vec<Variable>,
     pub file_type: FileType,
     pub selected_index: usize,
     pub filter: String,
-    pub param_input: String,
+    pub param_form: Option<ParamForm>,
     pub filtered_tasks: Vec<usize>,
-    pub task_history: Vec<TaskExecution>,
-    pub current_output: String,
+    pub collapsed_modules: HashSet<String>,
+    pub history: History,
+    pub history_view: HistoryView,
+    // help, graph, details and other text views
+    pub current_output: VecDeque<ShownLine>,
     pub show_output: bool,
-    pub output_scroll: u16,
+    // the panel shows `current_output` rather than the selected run
+    pub showing_text: bool,
+    // hide stdout so errors stand out
+    pub stderr_only: bool,
+    pub output_scroll: ScrollState,
+    pub output_max_scroll: u16,
     pub working_dir: PathBuf,
+    // the makefile or justfile picked on the command line or in the config,
+    // passed on to make/just since they would look for the default names
+    pub root_file: Option<PathBuf>,
+    pub execution_mode: ExecutionMode,
+    // run tasks inside a pseudo-terminal instead of pipes
+    pub use_pty: bool,
+    // one output tab per run, oldest first
+    pub runs: Vec<Run>,
+    pub selected_run: usize,
+    pub config: Config,
+    // shown instead of failing to start
+    pub config_errors: Vec<String>,
+    pub keymap: Keymap,
+    // the start of a chord typed so far
+    pub pending_keys: Sequence,
+    pub theme: Theme,
+    pub grace: GracePeriods,
+}
+
+// what to load, from the command line; unset fields fall back to the config
+// and then to looking for a makefile or justfile
+#[derive(Debug, Clone, Default)]
+pub struct Source {
+    pub file: Option<PathBuf>,
+    pub file_type: Option<FileType>,
+}
+
+// where a started task's output goes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Launch {
+    // a tab that takes over the output panel
+    Focused,
+    // a tab that starts without taking focus, for servers and watchers
+    Background,
+    // the real terminal, with the tui suspended
+    Interactive,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScrollState {
+    pub offset: u16,
+    // stick to the end while output keeps arriving
+    pub follow: bool,
 }
 
-#[derive(Debug, Clone)]
-pub struct TaskExecution {
+// which runs the history view lists; the selection indexes that list
+#[derive(Debug, Clone, Default)]
+pub struct HistoryView {
+    pub selected: usize,
+    // fuzzy match on the task name
+    pub filter: String,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Outcome {
+    #[default]
+    All,
+    Passed,
+    // non-zero exits, cancelled and timed out runs
+    Failed,
+}
+
+impl Outcome {
+    pub fn next(self) -> Self {
+        match self {
+            Outcome::All => Outcome::Passed,
+            Outcome::Passed => Outcome::Failed,
+            Outcome::Failed => Outcome::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Outcome::All => "all",
+            Outcome::Passed => "passed",
+            Outcome::Failed => "failed",
+        }
+    }
+
+    fn matches(self, status: RunStatus) -> bool {
+        match self {
+            Outcome::All => true,
+            Outcome::Passed => status.is_success(),
+            Outcome::Failed => !status.is_success(),
+        }
+    }
+}
+
+pub struct Run {
     pub task_name: String,
-    pub timestamp: String,
-    pub exit_code: i32,
-    pub output: String,
+    pub started: Instant,
+    pub started_at: DateTime<Local>,
+    // the last `MAX_SHOWN_LINES` lines of output; the full output goes to
+    // the run's history log
+    pub shown: VecDeque<ShownLine>,
+    // how many earlier lines no longer fit in `shown`
+    pub dropped: usize,
+    log: Option<RunLog>,
+    // set once the run has finished
+    pub status: Option<RunStatus>,
+    pub finished: Option<Instant>,
+    pub cancelling: bool,
+    pub background: bool,
+    // start again with the same arguments once this run has stopped
+    pub restart: bool,
+    pub arguments: Arguments,
+    // how long earlier runs of the task usually took
+    pub estimate: Option<Duration>,
+    pub scroll: ScrollState,
+    handle: Option<RunHandle>,
+}
+
+// an output line as the panel draws it; escape sequences are parsed once,
+// when the line arrives, rather than on every frame
+pub struct ShownLine {
+    pub stream: Stream,
+    pub line: Line<'static>,
+}
+
+impl ShownLine {
+    fn new(line: &OutputLine, theme: &Theme) -> Self {
+        let shown = match line.stream {
+            Stream::Stdout => ansi::to_line(&line.text, Style::default()),
+            Stream::Stderr => ansi::to_line(&line.text, theme.stderr),
+            Stream::Info => Line::raw(line.text.clone()),
+        };
+        Self {
+            stream: line.stream,
+            line: shown,
+        }
+    }
+}
+
+impl Run {
+    pub fn is_active(&self) -> bool {
+        self.status.is_none()
+    }
+
+    fn push(&mut self, line: OutputLine, theme: &Theme) {
+        if let Some(log) = &mut self.log {
+            if let Err(e) = log.write(&line) {
+                self.log = None;
+                let note = OutputLine::new(Stream::Info, format!("log not saved: {e}"));
+                self.shown.push_back(ShownLine::new(&note, theme));
+            }
+        }
+        self.shown.push_back(ShownLine::new(&line, theme));
+        while self.shown.len() > MAX_SHOWN_LINES {
+            self.shown.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(Instant::now) - self.started
+    }
 }
 
 impl App {
-    pub fn new(working_dir: PathBuf) -> Result<Self> {
-        let (tasks, file_type) = Parser::detect_and_parse(&working_dir)?;
-        let filtered_tasks: Vec<usize> = (0..tasks.len()).collect();
+    pub fn new(working_dir: PathBuf, source: Source) -> Result<Self> {
+        let (config, mut config_errors) = Config::load(&working_dir);
+        let root_file = source
+            .file
+            .or_else(|| config.file.as_ref().map(|file| working_dir.join(file)));
+        let parsed = match &root_file {
+            Some(file) => Parser::parse_file(file, &working_dir, source.file_type)?,
+            None => Parser::detect_and_parse(&working_dir, source.file_type)?,
+        };
+        config_errors.extend(parsed.errors);
+        // settings for a task that does not exist are most likely a typo
+        let mut unknown: Vec<&String> = config
+            .project_tasks
+            .iter()
+            .filter(|name| !parsed.tasks.iter().any(|t| t.name == **name))
+            .collect();
+        unknown.sort();
+        config_errors.extend(unknown.into_iter().map(|name| format!("tasks.{name}: there is no task named `{name}`")));
+        let (keymap, key_errors) = Keymap::new(&config.keys);
+        config_errors.extend(key_errors);
+        let (theme, theme_errors) = Theme::new(config.theme.as_deref(), &config.themes);
+        config_errors.extend(theme_errors);
+        let (grace, grace_errors) = GracePeriods::new(config.sigint_grace, config.sigterm_grace);
+        config_errors.extend(grace_errors);
+        let filtered_tasks: Vec<usize> = (0..parsed.tasks.len()).collect();
 
-        Ok(Self {
-            tasks,
-            file_type,
+        let mut app = Self {
+            tasks: parsed.tasks,
+            variables: parsed.variables,
+            file_type: parsed.file_type,
             selected_index: 0,
             filter: String::new(),
-            param_input: String::new(),
+            param_form: None,
             filtered_tasks,
-            task_history: Vec::new(),
-            current_output: String::new(),
+            collapsed_modules: HashSet::new(),
+            history: History::load(&working_dir),
+            history_view: HistoryView::default(),
+            current_output: VecDeque::new(),
             show_output: false,
-            output_scroll: 0,
+            showing_text: true,
+            stderr_only: false,
+            output_scroll: ScrollState::default(),
+            output_max_scroll: 0,
             working_dir,
-        })
+            root_file,
+            execution_mode: config.mode.unwrap_or(ExecutionMode::Delegate),
+            use_pty: false,
+            runs: Vec::new(),
+            selected_run: 0,
+            config,
+            config_errors,
+            keymap,
+            pending_keys: Vec::new(),
+            theme,
+            grace,
+        };
+        app.apply_filter();
+        if !app.config_errors.is_empty() {
+            app.show_text(app.config_error_text());
+        }
+        Ok(app)
+    }
+
+    pub fn config_error_text(&self) -> String {
+        format!(
+            "configuration problems:\n\n{}",
+            self.config_errors.join("\n")
+        )
     }
 
     pub fn update_filter(&mut self, filter: String) {
@@ -53,7 +280,15 @@ impl App {
 
     pub fn apply_filter(&mut self) {
         if self.filter.is_empty() {
-            self.filtered_tasks = (0..self.tasks.len()).collect();
+            // a collapsed module is represented by its first recipe only
+            let mut seen = HashSet::new();
+            self.filtered_tasks = (0..self.tasks.len())
+                .filter(|&i| !self.config.is_hidden(&self.tasks[i].name))
+                .filter(|&i| match self.collapsed_module(&self.tasks[i]) {
+                    Some(module) => seen.insert(module),
+                    None => true,
+                })
+                .collect();
         } else {
             use fuzzy_matcher::FuzzyMatcher;
             use fuzzy_matcher::skim::SkimMatcherV2;
@@ -63,6 +298,7 @@ impl App {
                 .tasks
                 .iter()
                 .enumerate()
+                .filter(|(_, task)| !self.config.is_hidden(&task.name))
                 .filter_map(|(i, task)| {
                     let name_score = matcher.fuzzy_match(&task.name, &self.filter).unwrap_or(0);
                     let desc_score = matcher.fuzzy_match(&task.description, &self.filter).unwrap_or(0);
@@ -77,6 +313,55 @@ impl App {
                 .map(|(i, _)| i)
                 .collect();
         }
+
+        // grouped tasks come first, group by group; the sort keeps file order
+        // within a group
+        let config = &self.config;
+        let tasks = &self.tasks;
+        self.filtered_tasks
+            .sort_by_key(|&i| config.group(&tasks[i].name).map_or(usize::MAX, |(idx, _)| idx));
+    }
+
+    pub fn collapsed_module(&self, task: &Task) -> Option<String> {
+        let module = task.module.as_deref()?;
+        self.collapsed_modules
+            .iter()
+            .find(|m| module == m.as_str() || module.starts_with(&format!("{m}::")))
+            .cloned()
+    }
+
+    pub fn module_task_count(&self, module: &str) -> usize {
+        let prefix = format!("{module}::");
+        self.tasks.iter().filter(|t| t.name.starts_with(&prefix)).count()
+    }
+
+    pub fn toggle_selected_module(&mut self) {
+        let Some(task) = self.selected_task() else {
+            return;
+        };
+
+        let module = match self.collapsed_module(task) {
+            Some(module) => {
+                self.collapsed_modules.remove(&module);
+                module
+            }
+            None => match task.module.clone() {
+                Some(module) => {
+                    self.collapsed_modules.insert(module.clone());
+                    module
+                }
+                None => return,
+            },
+        };
+
+        self.apply_filter();
+        // keep the cursor on the module's first row
+        let prefix = format!("{module}::");
+        self.selected_index = self
+            .filtered_tasks
+            .iter()
+            .position(|&i| self.tasks[i].name.starts_with(&prefix))
+            .unwrap_or(0);
     }
 
     pub fn selected_task(&self) -> Option<&Task> {
@@ -127,26 +412,444 @@ impl App {
         }
     }
 
-    pub fn add_to_history(&mut self, task_name: String, exit_code: i32, output: String) {
-        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        self.task_history.push(TaskExecution {
-            task_name,
-            timestamp,
-            exit_code,
-            output,
+    pub fn param_form(&self, task: &Task, launch: Launch) -> ParamForm {
+        let mut form = ParamForm::for_task(task, &self.variables, self.config.timeouts(&task.name));
+        form.launch = launch;
+        form
+    }
+
+    // how enter runs a task unless the config says otherwise
+    pub fn default_launch(&self, task: &Task) -> Launch {
+        if self.config.is_interactive(&task.name) {
+            Launch::Interactive
+        } else if self.config.is_background(&task.name) {
+            Launch::Background
+        } else {
+            Launch::Focused
+        }
+    }
+
+    pub fn invocation(&self, task: &Task, arguments: &Arguments) -> Invocation {
+        // make overrides change how the recipe expands; they are also
+        // exported so scripts called from the recipe can see them
+        let commands = if task.file_type == FileType::Makefile && !arguments.overrides.is_empty() {
+            Parser::expand_recipe(task, &self.variables, &arguments.overrides)
+        } else {
+            task.commands.clone()
+        };
+        let (commands, env) = arguments.apply(task, &commands);
+        Invocation {
+            task_name: task.name.clone(),
+            mode: self.execution_mode,
+            commands,
+            program: self.delegate_command(task, arguments),
+            working_dir: task.working_dir().map(|dir| dir.to_path_buf()),
+            env,
+            timeouts: self.timeouts(task, arguments),
+            pty: self.use_pty,
+            shell: self.config.shell.clone(),
+        }
+    }
+
+    // a timeout given in the form replaces the configured one for this run
+    pub fn timeouts(&self, task: &Task, arguments: &Arguments) -> Timeouts {
+        let mut timeouts = self.config.timeouts(&task.name);
+        if let Some(limit) = arguments.timeout {
+            timeouts.task = Some(limit).filter(|d| !d.is_zero());
+        }
+        timeouts
+    }
+
+    // in delegate mode make/just build prerequisites themselves; inline runs
+    // execute the dependency plan step by step
+    pub fn invocations(&self, task: &Task, arguments: &Arguments) -> Result<Vec<Invocation>> {
+        if self.execution_mode == ExecutionMode::Delegate {
+            return Ok(vec![self.invocation(task, arguments)]);
+        }
+
+        // make overrides apply to every target, recipe arguments only to the
+        // selected one; a dependency gets the arguments it is called with
+        let mut invocations = Vec::new();
+        for (step, args) in self.execution_plan(&task.name)? {
+            if let Some(call) = step.make_functions.first() {
+                bail!(
+                    "`{}` uses `{}`, which only make can evaluate; run it in delegate mode",
+                    step.name,
+                    make_reference(call)
+                );
+            }
+            if step.name == task.name {
+                invocations.push(self.invocation(step, arguments));
+                continue;
+            }
+            let mut dep_arguments = Arguments::from_call(step, args).map_err(|e| anyhow!(e))?;
+            dep_arguments.overrides = arguments.overrides.clone();
+            invocations.push(self.invocation(step, &dep_arguments));
+        }
+        Ok(invocations)
+    }
+
+    // `make target NAME=value` or `just module recipe args...`
+    fn delegate_command(&self, task: &Task, arguments: &Arguments) -> Vec<String> {
+        let file = self.root_file.as_ref().map(|file| file.display().to_string());
+        let mut program = match task.file_type {
+            FileType::Makefile => {
+                let mut program = vec!["make".to_string()];
+                if let Some(file) = file {
+                    program.extend(["-f".to_string(), file]);
+                }
+                program.push(task.name.clone());
+                program
+            }
+            FileType::Justfile => {
+                let mut program = vec!["just".to_string()];
+                if let Some(file) = file {
+                    program.extend(["--justfile".to_string(), file]);
+                }
+                program.extend(task.name.split("::").map(|s| s.to_string()));
+                program
+            }
+        };
+        program.extend(
+            arguments
+                .positional
+                .iter()
+                .flat_map(|(_, values)| values.iter().cloned()),
+        );
+        program.extend(arguments.overrides.iter().map(|(k, v)| format!("{k}={v}")));
+        program
+    }
+
+    pub fn add_to_history(
+        &mut self,
+        task_name: String,
+        arguments: &Arguments,
+        started: DateTime<Local>,
+        status: RunStatus,
+        output: &[OutputLine],
+    ) -> Result<()> {
+        let entry = history_entry(task_name, arguments, started, status);
+        self.history.record(entry, output)
+    }
+
+    // the runs the history view lists, newest first
+    pub fn history_entries(&self) -> Vec<&HistoryEntry> {
+        use fuzzy_matcher::FuzzyMatcher;
+        use fuzzy_matcher::skim::SkimMatcherV2;
+
+        let view = &self.history_view;
+        let matcher = SkimMatcherV2::default();
+        self.history
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| view.outcome.matches(entry.status))
+            .filter(|entry| view.filter.is_empty() || matcher.fuzzy_match(&entry.task_name, &view.filter).is_some())
+            .collect()
+    }
+
+    pub fn selected_history_entry(&self) -> Option<&HistoryEntry> {
+        self.history_entries().get(self.history_view.selected).copied()
+    }
+
+    // keeps the selection inside the list after the filters change
+    pub fn clamp_history_selection(&mut self) {
+        let last = self.history_entries().len().saturating_sub(1);
+        self.history_view.selected = self.history_view.selected.min(last);
+    }
+
+    pub fn move_history_selection(&mut self, delta: isize) {
+        self.history_view.selected = self.history_view.selected.saturating_add_signed(delta);
+        self.clamp_history_selection();
+    }
+
+    pub fn open_selected_history_entry(&mut self) {
+        let Some(entry) = self.selected_history_entry() else {
+            return;
+        };
+        let mut header = format!(
+            "{} at {} ({}, {:.1}s)",
+            entry.task_name,
+            entry.started.format("%Y-%m-%d %H:%M:%S"),
+            entry.status,
+            entry.duration.as_secs_f64()
+        );
+        if let Some(path) = self.history.log_path(entry) {
+            header.push_str(&format!("\nlog: {}", path.display()));
+        }
+        match self.history.read_log(entry) {
+            Ok(output) => {
+                let mut lines: Vec<OutputLine> = header
+                    .lines()
+                    .map(|line| OutputLine::new(Stream::Info, line.to_string()))
+                    .collect();
+                lines.push(OutputLine::new(Stream::Info, String::new()));
+                lines.extend(output);
+                self.show_lines(lines);
+            }
+            Err(e) => self.show_text(format!("{header}\n\n{e}")),
+        }
+    }
+
+    // a new run takes over the tab of an earlier, finished run of the same
+    // task; background runs leave the output panel as it is
+    pub fn start_run(&mut self, task_name: String, arguments: Arguments, background: bool, handle: RunHandle) {
+        let estimate = self.history.estimate(&task_name);
+        let started_at = Local::now();
+        let mut shown = VecDeque::new();
+        let log = self.history.start_log(&task_name, started_at).unwrap_or_else(|e| {
+            let note = OutputLine::new(Stream::Info, format!("log not saved: {e}"));
+            shown.push_back(ShownLine::new(&note, &self.theme));
+            None
         });
-        
-        if self.task_history.len() > 100 {
-            self.task_history.remove(0);
+        let run = Run {
+            task_name,
+            started: Instant::now(),
+            started_at,
+            shown,
+            dropped: 0,
+            log,
+            status: None,
+            finished: None,
+            cancelling: false,
+            background,
+            restart: false,
+            arguments,
+            estimate,
+            scroll: ScrollState {
+                offset: 0,
+                follow: true,
+            },
+            handle: Some(handle),
+        };
+        let idx = match self.runs.iter().position(|r| r.task_name == run.task_name) {
+            Some(idx) => {
+                self.runs[idx] = run;
+                idx
+            }
+            None => {
+                self.runs.push(run);
+                self.runs.len() - 1
+            }
+        };
+        if !background {
+            self.show_run(idx);
+        }
+    }
+
+    // moves whatever the running tasks have printed since the last tick into
+    // their tabs, and records each run once it has finished
+    pub fn drain_run_events(&mut self) {
+        for run in &mut self.runs {
+            if run.handle.is_none() {
+                continue;
+            }
+            while let Some(Ok(event)) = run.handle.as_mut().map(|handle| handle.events.try_recv()) {
+                match event {
+                    RunEvent::Line(line) => run.push(line, &self.theme),
+                    RunEvent::Finished(status) => {
+                        run.status = Some(status);
+                        run.finished = Some(Instant::now());
+                    }
+                }
+            }
+            let Some(status) = run.status else {
+                continue;
+            };
+            run.handle = None;
+            let entry = history_entry(run.task_name.clone(), &run.arguments, run.started_at, status);
+            if let Err(e) = self.history.finish(entry, run.log.take()) {
+                let line = OutputLine::new(Stream::Info, format!("history not saved: {e}"));
+                run.push(line, &self.theme);
+            }
+        }
+    }
+
+    pub fn find_active_run(&self, task_name: &str) -> Option<&Run> {
+        self.runs.iter().find(|run| run.task_name == task_name && run.is_active())
+    }
+
+    fn run_index(&self, task_name: &str) -> Option<usize> {
+        self.runs.iter().position(|run| run.task_name == task_name)
+    }
+
+    // shows the tab of the task's latest run
+    pub fn attach_run(&mut self, task_name: &str) -> bool {
+        match self.run_index(task_name) {
+            Some(idx) => {
+                self.show_run(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn stop_run(&mut self, task_name: &str) -> bool {
+        match self.run_index(task_name) {
+            Some(idx) => self.cancel_run(idx),
+            None => false,
+        }
+    }
+
+    // a running task is stopped first and started again once it has exited
+    pub fn restart_run(&mut self, task_name: &str) -> bool {
+        let Some(idx) = self.run_index(task_name) else {
+            return false;
+        };
+        self.cancel_run(idx);
+        self.runs[idx].restart = true;
+        true
+    }
+
+    // runs waiting to be restarted that have stopped by now
+    pub fn take_restarts(&mut self) -> Vec<(String, Arguments, bool)> {
+        self.runs
+            .iter_mut()
+            .filter(|run| run.restart && !run.is_active())
+            .map(|run| {
+                run.restart = false;
+                (run.task_name.clone(), run.arguments.clone(), run.background)
+            })
+            .collect()
+    }
+
+    // asks every running task to stop, e.g. before quitting
+    pub fn stop_all_runs(&mut self) {
+        for idx in 0..self.runs.len() {
+            self.runs[idx].restart = false;
+            self.cancel_run(idx);
+        }
+    }
+
+    pub fn viewed_run(&self) -> Option<&Run> {
+        if self.showing_text || !self.show_output {
+            return None;
+        }
+        self.runs.get(self.selected_run)
+    }
+
+    pub fn cancel_viewed_run(&mut self) -> bool {
+        !self.showing_text && self.cancel_run(self.selected_run)
+    }
+
+    // asks a run to stop; it is recorded once its processes exit
+    fn cancel_run(&mut self, idx: usize) -> bool {
+        match self.runs.get_mut(idx) {
+            Some(run) if run.is_active() => {
+                if let Some(handle) = &run.handle {
+                    handle.cancel();
+                }
+                run.cancelling = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn show_run(&mut self, idx: usize) {
+        if idx < self.runs.len() {
+            self.selected_run = idx;
+            self.showing_text = false;
+            self.show_output = true;
+        }
+    }
+
+    pub fn next_run(&mut self) {
+        if !self.runs.is_empty() {
+            let next = if self.showing_text { self.selected_run } else { self.selected_run + 1 };
+            self.show_run(next % self.runs.len());
+        }
+    }
+
+    pub fn prev_run(&mut self) {
+        if !self.runs.is_empty() {
+            let len = self.runs.len();
+            let prev = if self.showing_text { self.selected_run } else { self.selected_run + len - 1 };
+            self.show_run(prev % len);
         }
     }
 
+    // finished tabs can be closed; running ones have to be cancelled first
+    pub fn close_viewed_run(&mut self) {
+        if self.viewed_run().is_some_and(|run| !run.is_active()) {
+            self.runs.remove(self.selected_run);
+            self.selected_run = self.selected_run.min(self.runs.len().saturating_sub(1));
+            if self.runs.is_empty() {
+                self.showing_text = true;
+                self.show_output = false;
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.runs.iter().any(Run::is_active)
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.runs.iter().filter(|run| run.is_active()).count()
+    }
+
+    pub fn show_text(&mut self, text: String) {
+        self.show_lines(
+            text.lines()
+                .map(|line| OutputLine::new(Stream::Info, line.to_string()))
+                .collect(),
+        );
+    }
+
+    pub fn show_lines(&mut self, lines: Vec<OutputLine>) {
+        self.current_output = lines.iter().map(|line| ShownLine::new(line, &self.theme)).collect();
+        self.output_scroll = ScrollState::default();
+        self.showing_text = true;
+        self.show_output = true;
+    }
+
+    pub fn visible_output(&self) -> impl Iterator<Item = &ShownLine> {
+        let lines = match self.viewed_run() {
+            Some(run) => &run.shown,
+            None => &self.current_output,
+        };
+        lines
+            .iter()
+            .filter(|line| !(self.stderr_only && line.stream == Stream::Stdout))
+    }
+
+    // the scroll position of whatever the panel shows; each tab keeps its own
+    pub fn view_scroll(&self) -> ScrollState {
+        match self.viewed_run() {
+            Some(run) => run.scroll,
+            None => self.output_scroll,
+        }
+    }
+
+    fn view_scroll_mut(&mut self) -> (&mut ScrollState, bool) {
+        if !self.showing_text {
+            if let Some(run) = self.runs.get_mut(self.selected_run) {
+                let active = run.is_active();
+                return (&mut run.scroll, active);
+            }
+        }
+        (&mut self.output_scroll, false)
+    }
+
     pub fn scroll_output_up(&mut self, lines: u16) {
-        self.output_scroll = self.output_scroll.saturating_sub(lines);
+        let max_scroll = self.output_max_scroll;
+        let (scroll, _) = self.view_scroll_mut();
+        if scroll.follow {
+            scroll.follow = false;
+            scroll.offset = max_scroll;
+        }
+        scroll.offset = scroll.offset.saturating_sub(lines);
     }
 
     pub fn scroll_output_down(&mut self, lines: u16) {
-        self.output_scroll = self.output_scroll.saturating_add(lines);
+        let max_scroll = self.output_max_scroll;
+        let (scroll, active) = self.view_scroll_mut();
+        scroll.offset = scroll.offset.saturating_add(lines).min(max_scroll);
+        // scrolling to the end of a running task's output resumes following it
+        if active && scroll.offset >= max_scroll {
+            scroll.follow = true;
+        }
     }
 
     pub fn has_tasks(&self) -> bool {
@@ -161,13 +864,136 @@ impl App {
         if let Some(task) = self.tasks.iter().find(|t| t.name == task_name) {
             task.dependencies
                 .iter()
-                .filter_map(|dep| self.tasks.iter().find(|t| t.name == *dep))
+                .filter_map(|dep| self.tasks.iter().find(|t| t.name == dep.name))
                 .collect()
         } else {
             Vec::new()
         }
     }
 
+    pub fn variable_listing(&self) -> String {
+        self.variables
+            .iter()
+            .map(|v| format!("{v}\n"))
+            .collect()
+    }
+
+    pub fn task_details(&self, task: &Task) -> String {
+        let mut lines = format!("task: {}\n", task.name);
+        let file = task
+            .location
+            .file
+            .strip_prefix(&self.working_dir)
+            .unwrap_or(&task.location.file);
+        lines.push_str(&format!("defined in: {}:{}\n", file.display(), task.location.line));
+        if !task.description.is_empty() {
+            lines.push_str(&format!("description: {}\n", task.description));
+        }
+        if !task.dependencies.is_empty() {
+            lines.push_str(&format!("dependencies: {}\n", join_dependencies(&task.dependencies)));
+        }
+        if !task.subsequents.is_empty() {
+            lines.push_str(&format!("then: {}\n", join_dependencies(&task.subsequents)));
+        }
+        if !task.make_functions.is_empty() {
+            let calls: Vec<String> = task.make_functions.iter().map(|f| make_reference(f)).collect();
+            lines.push_str(&format!("runs only through make: uses {}\n", calls.join(", ")));
+        }
+        if let Some((_, group)) = self.config.group(&task.name) {
+            lines.push_str(&format!("group: {group}\n"));
+        }
+        let timeouts = self.config.timeouts(&task.name);
+        if let Some(limit) = timeouts.task {
+            lines.push_str(&format!("timeout: {}\n", format_duration(limit)));
+        }
+        if let Some(limit) = timeouts.command {
+            lines.push_str(&format!("command timeout: {}\n", format_duration(limit)));
+        }
+
+        let durations = self.history.durations(&task.name);
+        if let Some(stats) = DurationStats::new(&durations) {
+            let secs = |d: Duration| format!("{:.1}s", d.as_secs_f64());
+            lines.push_str(&format!("durations ({} successful runs):\n", stats.runs));
+            lines.push_str(&format!(
+                "  last {} · avg {} · p50 {} · p95 {}\n",
+                secs(stats.last),
+                secs(stats.average),
+                secs(stats.p50),
+                secs(stats.p95)
+            ));
+            let trend = history::sparkline(&durations, SPARKLINE_RUNS);
+            if !trend.is_empty() {
+                lines.push_str(&format!("  trend {trend}\n"));
+            }
+            // the point of the trend: a task that quietly got slower
+            let ratio = stats.last.as_secs_f64() / stats.p50.as_secs_f64().max(0.001);
+            if stats.runs > 1 && ratio >= SLOWDOWN_RATIO {
+                lines.push_str(&format!("  last run took {ratio:.1}x the median\n"));
+            }
+        }
+
+        if !task.parameters.is_empty() {
+            lines.push_str("parameters:\n");
+            for param in &task.parameters {
+                let kind = if param.variadic.is_some() {
+                    "variadic"
+                } else if param.is_required() {
+                    "required"
+                } else {
+                    "optional"
+                };
+                lines.push_str(&format!("  {param} ({kind})\n"));
+            }
+        }
+
+        lines.push_str("\nrecipe:\n");
+        for (cmd, raw) in task.commands.iter().zip(&task.raw_commands) {
+            lines.push_str(&format!("  $ {cmd}\n"));
+            if raw != cmd {
+                lines.push_str(&format!("    raw: {raw}\n"));
+            }
+        }
+        lines
+    }
+
+    // prerequisites in declaration order, then the task, then the justfile
+    // dependencies after `&&`; each task runs once per set of call arguments
+    pub fn execution_plan(&self, task_name: &str) -> Result<Vec<(&Task, &[String])>> {
+        let mut plan = Vec::new();
+        let mut stack = Vec::new();
+        self.visit_plan(task_name, &[], &mut stack, &mut plan)?;
+        Ok(plan)
+    }
+
+    fn visit_plan<'a>(
+        &'a self,
+        task_name: &str,
+        args: &'a [String],
+        stack: &mut Vec<&'a str>,
+        plan: &mut Vec<(&'a Task, &'a [String])>,
+    ) -> Result<()> {
+        if plan.iter().any(|(t, a)| t.name == task_name && *a == args) {
+            return Ok(());
+        }
+        if let Some(pos) = stack.iter().position(|name| *name == task_name) {
+            bail!("dependency cycle: {} -> {}", stack[pos..].join(" -> "), task_name);
+        }
+        let Some(task) = self.tasks.iter().find(|t| t.name == task_name) else {
+            return Ok(());
+        };
+
+        stack.push(&task.name);
+        for dep in &task.dependencies {
+            self.visit_plan(&dep.name, &dep.args, stack, plan)?;
+        }
+        stack.pop();
+        plan.push((task, args));
+        for dep in &task.subsequents {
+            self.visit_plan(&dep.name, &dep.args, stack, plan)?;
+        }
+        Ok(())
+    }
+
     pub fn dependency_tree(&self, task_name: &str) -> String {
         let mut visited = HashSet::new();
         let mut lines = String::new();
@@ -196,3 +1022,84 @@ impl App {
         }
     }
 }
+
+// a run that has just finished
+fn history_entry(task_name: String, arguments: &Arguments, started: DateTime<Local>, status: RunStatus) -> HistoryEntry {
+    let finished = Local::now();
+    HistoryEntry {
+        task_name,
+        arguments: arguments.into(),
+        started,
+        finished,
+        duration: (finished - started).to_std().unwrap_or_default(),
+        status,
+        log: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // an app over `justfile`, in a directory of the test's own
+    fn app_with(test: &str, justfile: &str) -> App {
+        let dir = std::env::temp_dir().join(format!("lazymake-app-{test}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("justfile"), justfile).unwrap();
+        let app = App::new(dir.clone(), Source::default()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        app
+    }
+
+    fn plan(app: &App, task: &str) -> Vec<String> {
+        app.execution_plan(task)
+            .unwrap()
+            .into_iter()
+            .map(|(task, args)| match args {
+                [] => task.name.clone(),
+                args => format!("{} {}", task.name, args.join(" ")),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dependencies_run_first_in_order() {
+        let app = app_with(
+            "order",
+            "build: fmt lint\n\techo build\n\nlint: fmt\n\techo lint\n\nfmt:\n\techo fmt\n",
+        );
+        assert_eq!(plan(&app, "build"), ["fmt", "lint", "build"]);
+    }
+
+    #[test]
+    fn each_task_runs_once_per_set_of_arguments() {
+        let app = app_with(
+            "arguments",
+            "all: (greet \"a\") (greet \"b\") (greet \"a\") greet-twice\n\techo all\n\n\
+             greet-twice: (greet \"b\")\n\techo twice\n\n\
+             greet name=\"x\":\n\techo {{name}}\n",
+        );
+        assert_eq!(plan(&app, "all"), ["greet \"a\"", "greet \"b\"", "greet-twice", "all"]);
+    }
+
+    #[test]
+    fn subsequents_run_after_the_task() {
+        let app = app_with(
+            "subsequents",
+            "release: build && publish notify\n\techo release\n\n\
+             build:\n\techo build\n\npublish: build\n\techo publish\n\nnotify:\n\techo notify\n",
+        );
+        assert_eq!(plan(&app, "release"), ["build", "release", "publish", "notify"]);
+    }
+
+    #[test]
+    fn dependency_cycles_are_an_error() {
+        let app = app_with(
+            "cycle",
+            "a: b\n\techo a\n\nb: c\n\techo b\n\nc: a\n\techo c\n",
+        );
+        let error = app.execution_plan("a").unwrap_err();
+        assert_eq!(error.to_string(), "dependency cycle: a -> b -> c -> a");
+    }
+}
@@ -0,0 +1,197 @@
+use crate::app::{App, Launch, Source};
+use crate::config::parse_duration;
+use crate::executor::{Executor, OutputLine, RunEvent, RunStatus, Stream};
+use crate::params::Arguments;
+use crate::parser::FileType;
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Browse and run makefile targets and justfile recipes.
+///
+/// Without a command, lazymake opens the interactive task list.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Makefile or justfile to load instead of looking for one
+    #[arg(short, long, global = true, value_name = "PATH")]
+    pub file: Option<PathBuf>,
+
+    /// Project directory [default: the current directory]
+    #[arg(short = 'C', long, global = true, value_name = "PATH")]
+    pub dir: Option<PathBuf>,
+
+    /// Read the file as this type instead of going by its name
+    #[arg(short = 't', long = "type", global = true, value_name = "TYPE")]
+    pub file_type: Option<TypeArg>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum TypeArg {
+    #[value(alias = "makefile")]
+    Make,
+    #[value(alias = "justfile")]
+    Just,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print the tasks and exit
+    List,
+    /// Run a task without the interface and exit with its exit code
+    Run {
+        task: String,
+        /// Recipe arguments for a justfile, NAME=value overrides for a makefile
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+        /// Stop the task after this long, e.g. 30s or 5m; 0 for no limit
+        #[arg(long, value_parser = parse_duration)]
+        timeout: Option<Duration>,
+    },
+    /// Print the dependency tree of a task
+    Graph { task: String },
+}
+
+impl Cli {
+    // the project directory and what to load from it; `--file` is relative
+    // to where lazymake was started, like any other path on the command line
+    pub fn source(&self) -> Result<(PathBuf, Source)> {
+        let current_dir = std::env::current_dir()?;
+        let dir = match &self.dir {
+            Some(dir) => current_dir.join(dir),
+            None => current_dir.clone(),
+        };
+        if !dir.is_dir() {
+            return Err(anyhow!("{} is not a directory", dir.display()));
+        }
+        let source = Source {
+            file: self.file.as_ref().map(|file| current_dir.join(file)),
+            file_type: self.file_type.map(|file_type| match file_type {
+                TypeArg::Make => FileType::Makefile,
+                TypeArg::Just => FileType::Justfile,
+            }),
+        };
+        Ok((dir, source))
+    }
+}
+
+// problems that the tui would list in its output panel
+pub fn report_config_errors(app: &App) {
+    if !app.config_errors.is_empty() {
+        eprintln!("lazymake: {}\n", app.config_error_text());
+    }
+}
+
+// the visible tasks in task list order, `name params  # description`
+pub fn list(app: &App) {
+    let rows: Vec<(String, &str)> = app
+        .filtered_tasks
+        .iter()
+        .map(|&idx| {
+            let task = &app.tasks[idx];
+            let mut name = task.name.clone();
+            for param in &task.parameters {
+                name.push_str(&format!(" {param}"));
+            }
+            (name, task.description.as_str())
+        })
+        .collect();
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (name, description) in rows {
+        if description.is_empty() {
+            println!("{name}");
+        } else {
+            println!("{name:<width$}  # {description}");
+        }
+    }
+}
+
+pub fn graph(app: &App, task_name: &str) -> Result<()> {
+    find_task(app, task_name)?;
+    print!("{}", app.dependency_tree(task_name));
+    Ok(())
+}
+
+// the task's output goes to stdout and stderr as it arrives; the run is
+// recorded in the history like one started from the tui. ctrl+c cancels the
+// task the way the tui does
+pub async fn run(
+    app: &mut App,
+    task_name: &str,
+    args: &[String],
+    timeout: Option<Duration>,
+) -> Result<ExitCode> {
+    let task = find_task(app, task_name)?.clone();
+    let mut arguments = Arguments::from_args(&task, args).map_err(|e| anyhow!(e))?;
+    arguments.timeout = timeout;
+    let invocations = app.invocations(&task, &arguments)?;
+    let executor = Executor::new(app.working_dir.clone(), app.grace);
+    let started = chrono::Local::now();
+
+    let (status, output) = if app.default_launch(&task) == Launch::Interactive {
+        let status = executor.run_attached(&invocations).await;
+        let note = "ran interactively; output went to the terminal".to_string();
+        (status, vec![OutputLine::new(Stream::Info, note)])
+    } else {
+        let mut handle = executor.start(invocations);
+        let mut output = Vec::new();
+        let status = loop {
+            tokio::select! {
+                event = handle.events.recv() => match event {
+                    Some(RunEvent::Line(line)) => {
+                        print_line(&line);
+                        output.push(line);
+                    }
+                    Some(RunEvent::Finished(status)) => break status,
+                    None => break RunStatus::Cancelled,
+                },
+                _ = tokio::signal::ctrl_c() => handle.cancel(),
+            }
+        };
+        (status, output)
+    };
+
+    if !status.is_success() {
+        eprintln!("lazymake: {} failed ({})", task.name, status);
+    }
+    if let Err(e) = app.add_to_history(task.name.clone(), &arguments, started, status, &output) {
+        eprintln!("lazymake: history not saved: {e}");
+    }
+    Ok(exit_code(status))
+}
+
+fn find_task<'a>(app: &'a App, task_name: &str) -> Result<&'a crate::parser::Task> {
+    app.tasks
+        .iter()
+        .find(|task| task.name == task_name)
+        .ok_or_else(|| anyhow!("there is no task named `{task_name}`, see `lazymake list`"))
+}
+
+// lazymake's own notes (steps, summaries) go to stderr with the task's
+// errors; a closed pipe, `lazymake run build | head`, does not stop the task
+fn print_line(line: &OutputLine) {
+    let _ = match line.stream {
+        Stream::Stdout => writeln!(std::io::stdout(), "{}", line.text),
+        Stream::Stderr | Stream::Info => writeln!(std::io::stderr(), "{}", line.text),
+    };
+}
+
+// the task's own exit code; 130 and 124 as a shell and timeout(1) report
+// an interrupted and a timed out command
+fn exit_code(status: RunStatus) -> ExitCode {
+    match status {
+        RunStatus::Exited(code) => ExitCode::from(u8::try_from(code).unwrap_or(1)),
+        RunStatus::Cancelled => ExitCode::from(130),
+        RunStatus::TimedOut(_) => ExitCode::from(124),
+    }
+}
@@ -0,0 +1,436 @@
+use crate::executor::ExecutionMode;
+use anyhow::{anyhow, Result};
+use glob::Pattern;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const PROJECT_CONFIG: &str = ".lazymake.toml";
+
+// `$XDG_CONFIG_HOME/lazymake/config.toml` and `.lazymake.toml` in the
+// project directory share this layout; project settings win
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    // the makefile or justfile to load, relative to the project directory
+    pub file: Option<PathBuf>,
+    pub mode: Option<ExecutionMode>,
+    // runs inline recipe lines, e.g. `bash -euo pipefail -c`; the line is
+    // passed as the last argument
+    #[serde(default, deserialize_with = "shell")]
+    pub shell: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "duration")]
+    pub timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "duration")]
+    pub command_timeout: Option<Duration>,
+    // how long a cancelled task gets after SIGINT, and then after SIGTERM
+    #[serde(default, deserialize_with = "duration")]
+    pub sigint_grace: Option<Duration>,
+    #[serde(default, deserialize_with = "duration")]
+    pub sigterm_grace: Option<Duration>,
+    // tasks left out of the task list, as glob patterns
+    #[serde(default, deserialize_with = "patterns")]
+    pub hide: Vec<Pattern>,
+    // the task list shows grouped tasks first, in the order given here
+    #[serde(default)]
+    pub groups: Vec<Group>,
+    // a bundled theme or one of `themes`
+    pub theme: Option<String>,
+    #[serde(default)]
+    pub themes: HashMap<String, ThemeConfig>,
+    // `preset` and per-action keys, see `keymap`
+    #[serde(default)]
+    pub keys: HashMap<String, toml::Value>,
+    #[serde(default)]
+    pub tasks: HashMap<String, TaskConfig>,
+    // task names the project file has settings for; the global file may
+    // name tasks of any project
+    #[serde(skip)]
+    pub project_tasks: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Group {
+    pub name: String,
+    #[serde(deserialize_with = "patterns")]
+    pub tasks: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskConfig {
+    #[serde(default, deserialize_with = "duration")]
+    pub timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "duration")]
+    pub command_timeout: Option<Duration>,
+    // runs on the real terminal so the task can prompt for input
+    pub interactive: Option<bool>,
+    // starts without taking over the output panel, for servers and watchers
+    pub background: Option<bool>,
+}
+
+// style slots over a bundled theme, `stderr = "bold red"`; checked when the
+// theme is built so a typo does not drop the whole file
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ThemeConfig {
+    pub base: Option<String>,
+    #[serde(flatten)]
+    pub slots: HashMap<String, String>,
+}
+
+// a zero duration means no limit
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Timeouts {
+    // the whole task, including every recipe line
+    pub task: Option<Duration>,
+    // each recipe line on its own
+    pub command: Option<Duration>,
+}
+
+impl Config {
+    // the global config overlaid with the project one; a file that cannot be
+    // used is reported and skipped so the other one still applies
+    pub fn load(project_dir: &Path) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let mut errors = Vec::new();
+        let global = xdg_dir("XDG_CONFIG_HOME", ".config").map(|dir| (dir.join("config.toml"), false));
+        for (path, is_project) in global.into_iter().chain([(project_dir.join(PROJECT_CONFIG), true)]) {
+            match Self::load_file(&path, project_dir, is_project, &mut errors) {
+                Ok(Some(layer)) => config = config.merge(layer),
+                Ok(None) => {}
+                Err(e) => errors.push(format!("{e} (file ignored)")),
+            }
+        }
+        (config, errors)
+    }
+
+    fn load_file(
+        path: &Path,
+        project_dir: &Path,
+        is_project: bool,
+        errors: &mut Vec<String>,
+    ) -> Result<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot read {}: {e}", path.display()))?;
+        let mut config: Self = toml::from_str(&content).map_err(|e| {
+            let line = e
+                .span()
+                .map_or(1, |span| content[..span.start].matches('\n').count() + 1);
+            anyhow!("{}:{}: {}", path.display(), line, e.message())
+        })?;
+        config
+            .validate()
+            .map_err(|message| anyhow!("{}: {message}", path.display()))?;
+        // `file` names a file of one project, so only the project config can
+        // set it; a bad `file` is dropped and the rest of the file still applies
+        if let Some(file) = config.file.take() {
+            if !is_project {
+                errors.push(format!(
+                    "{}: file: only applies to one project, set it in {PROJECT_CONFIG} instead (ignored)",
+                    path.display()
+                ));
+            } else if !project_dir.join(&file).is_file() {
+                errors.push(format!(
+                    "{}: file: `{}` does not exist in {} (ignored)",
+                    path.display(),
+                    file.display(),
+                    project_dir.display()
+                ));
+            } else {
+                config.file = Some(file);
+            }
+        }
+        if is_project {
+            config.project_tasks = config.tasks.keys().cloned().collect();
+        }
+        Ok(Some(config))
+    }
+
+    // checks that cannot be expressed in the toml layout
+    fn validate(&self) -> Result<(), String> {
+        if self.shell.as_ref().is_some_and(|shell| shell.is_empty()) {
+            return Err("shell: must name a program, e.g. \"bash -c\"".to_string());
+        }
+        if self.groups.iter().any(|group| group.name.trim().is_empty()) {
+            return Err("groups: every group needs a name".to_string());
+        }
+        for (name, task) in &self.tasks {
+            if task.interactive == Some(true) && task.background == Some(true) {
+                return Err(format!("task `{name}` cannot be both interactive and background"));
+            }
+        }
+        Ok(())
+    }
+
+    // settings in `other` replace ours; lists are combined
+    fn merge(mut self, other: Self) -> Self {
+        for (name, task) in other.tasks {
+            let base = self.tasks.remove(&name).unwrap_or_default();
+            self.tasks.insert(
+                name,
+                TaskConfig {
+                    timeout: task.timeout.or(base.timeout),
+                    command_timeout: task.command_timeout.or(base.command_timeout),
+                    interactive: task.interactive.or(base.interactive),
+                    background: task.background.or(base.background),
+                },
+            );
+        }
+        // a group defined again replaces the earlier one
+        let mut groups = other.groups;
+        let kept: Vec<Group> = self
+            .groups
+            .into_iter()
+            .filter(|group| !groups.iter().any(|g| g.name == group.name))
+            .collect();
+        groups.extend(kept);
+        let mut hide = self.hide;
+        hide.extend(other.hide);
+        let mut keys = self.keys;
+        keys.extend(other.keys);
+        let mut themes = self.themes;
+        themes.extend(other.themes);
+
+        Self {
+            file: other.file.or(self.file),
+            mode: other.mode.or(self.mode),
+            shell: other.shell.or(self.shell),
+            timeout: other.timeout.or(self.timeout),
+            command_timeout: other.command_timeout.or(self.command_timeout),
+            sigint_grace: other.sigint_grace.or(self.sigint_grace),
+            sigterm_grace: other.sigterm_grace.or(self.sigterm_grace),
+            hide,
+            groups,
+            theme: other.theme.or(self.theme),
+            themes,
+            keys,
+            tasks: self.tasks,
+            project_tasks: [self.project_tasks, other.project_tasks].concat(),
+        }
+    }
+
+    pub fn is_interactive(&self, task_name: &str) -> bool {
+        self.tasks.get(task_name).and_then(|t| t.interactive).unwrap_or(false)
+    }
+
+    pub fn is_background(&self, task_name: &str) -> bool {
+        self.tasks.get(task_name).and_then(|t| t.background).unwrap_or(false)
+    }
+
+    pub fn is_hidden(&self, task_name: &str) -> bool {
+        self.hide.iter().any(|pattern| pattern.matches(task_name))
+    }
+
+    // the first group listing the task, with its position
+    pub fn group(&self, task_name: &str) -> Option<(usize, &str)> {
+        self.groups
+            .iter()
+            .enumerate()
+            .find(|(_, group)| group.tasks.iter().any(|p| p.matches(task_name)))
+            .map(|(idx, group)| (idx, group.name.as_str()))
+    }
+
+    // task settings win over the top-level ones
+    pub fn timeouts(&self, task_name: &str) -> Timeouts {
+        let task = self.tasks.get(task_name);
+        let limit = |value: Option<Duration>| value.filter(|d| !d.is_zero());
+        Timeouts {
+            task: limit(task.and_then(|t| t.timeout).or(self.timeout)),
+            command: limit(
+                task.and_then(|t| t.command_timeout)
+                    .or(self.command_timeout),
+            ),
+        }
+    }
+}
+
+// `$<var>/lazymake`, or `~/<fallback>/lazymake` when the variable is unset
+// or not an absolute path, as the xdg spec asks
+pub fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    let base = std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(fallback)))?;
+    Some(base.join("lazymake"))
+}
+
+// the entries of a free-form config table by name, so problems found in
+// them are reported in the same order every time
+pub fn sorted_entries<V>(table: &HashMap<String, V>) -> Vec<(&String, &V)> {
+    let mut entries: Vec<(&String, &V)> = table.iter().collect();
+    entries.sort_by_key(|(name, _)| *name);
+    entries
+}
+
+// `90`, `90s`, `500ms`, `5m`, `1h30m`
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    if let Ok(seconds) = text.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let invalid = || format!("`{text}` is not a duration (e.g. 30s, 5m, 1h30m)");
+    if text.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let value: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let part = match &rest[..unit] {
+            "ms" => Some(Duration::from_millis(value)),
+            "s" => Some(Duration::from_secs(value)),
+            "m" => value.checked_mul(60).map(Duration::from_secs),
+            "h" => value.checked_mul(3600).map(Duration::from_secs),
+            _ => return Err(invalid()),
+        };
+        total = part
+            .and_then(|part| total.checked_add(part))
+            .ok_or_else(invalid)?;
+        rest = &rest[unit..];
+    }
+    Ok(total)
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds == 0 {
+        return format!("{}ms", duration.as_millis());
+    }
+    [
+        (seconds / 3600, "h"),
+        (seconds % 3600 / 60, "m"),
+        (seconds % 60, "s"),
+    ]
+    .into_iter()
+    .filter(|(value, _)| *value > 0)
+    .map(|(value, unit)| format!("{value}{unit}"))
+    .collect()
+}
+
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Seconds(u64),
+        Text(String),
+    }
+
+    match Value::deserialize(deserializer)? {
+        Value::Seconds(seconds) => Ok(Some(Duration::from_secs(seconds))),
+        Value::Text(text) => parse_duration(&text)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+// `"bash -c"` or `["bash", "-c"]`
+fn shell<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Line(String),
+        Words(Vec<String>),
+    }
+
+    Ok(Some(match Value::deserialize(deserializer)? {
+        Value::Line(line) => line.split_whitespace().map(str::to_string).collect(),
+        Value::Words(words) => words,
+    }))
+}
+
+fn patterns<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Pattern>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|text| {
+            Pattern::new(text).map_err(|e| {
+                serde::de::Error::custom(format!("`{text}` is not a valid pattern: {e}"))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_with_units() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration(" 1h30m "), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1m30s250ms"), Ok(Duration::from_millis(90_250)));
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for text in ["", "h", "5x", "1.5h", "m5", "-1s"] {
+            assert!(parse_duration(text).is_err(), "{text:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn overflowing_durations_are_rejected() {
+        assert!(parse_duration("9999999999999999h").is_err());
+        assert!(parse_duration("99999999999999999999").is_err());
+        assert!(parse_duration(&format!("{}s1s", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn formatted_durations() {
+        assert_eq!(format_duration(Duration::from_millis(250)), "250ms");
+        assert_eq!(format_duration(Duration::from_secs(5400)), "1h30m");
+        assert_eq!(format_duration(Duration::from_secs(61)), "1m1s");
+    }
+
+    // `test` keeps the directories of tests running in parallel apart
+    fn load_layer(test: &str, content: &str, is_project: bool) -> (Option<Config>, Vec<String>) {
+        let dir = std::env::temp_dir().join(format!("lazymake-config-{test}-{is_project}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Makefile"), "all:\n").unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, content).unwrap();
+        let mut errors = Vec::new();
+        let config = Config::load_file(&path, &dir, is_project, &mut errors).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        (config, errors)
+    }
+
+    #[test]
+    fn file_is_only_read_from_the_project_config() {
+        let (config, errors) = load_layer("global-file", "file = \"Makefile\"\nmode = \"inline\"", false);
+        let config = config.unwrap();
+        assert_eq!(config.file, None);
+        assert_eq!(config.mode, Some(ExecutionMode::Inline));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("file: only applies to one project"));
+
+        let (config, errors) = load_layer("project-file", "file = \"Makefile\"", true);
+        assert_eq!(config.unwrap().file, Some(PathBuf::from("Makefile")));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn a_missing_file_keeps_the_other_settings() {
+        let (config, errors) = load_layer("missing-file", "file = \"nope.mk\"\nshell = \"bash -c\"", true);
+        let config = config.unwrap();
+        assert_eq!(config.file, None);
+        assert_eq!(config.shell, Some(vec!["bash".to_string(), "-c".to_string()]));
+        assert!(errors[0].contains("file: `nope.mk` does not exist"));
+    }
+}
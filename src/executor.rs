@@ -1,67 +1,672 @@
-use anyhow::Result;
-use std::process::Command;
-use std::path::PathBuf;
+use crate::config::{format_duration, Timeouts};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+use tokio::time::Instant;
 
+#[cfg(unix)]
+mod pty;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    // hand the task to `make`/`just` so it behaves exactly as on the command line
+    Delegate,
+    // run each recipe line through the shell ourselves
+    Inline,
+}
+
+impl ExecutionMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            ExecutionMode::Delegate => ExecutionMode::Inline,
+            ExecutionMode::Inline => ExecutionMode::Delegate,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExecutionMode::Delegate => "delegate",
+            ExecutionMode::Inline => "inline",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Invocation {
+    pub task_name: String,
+    pub mode: ExecutionMode,
+    // recipe lines, used in inline mode
+    pub commands: Vec<String>,
+    // `make <target> ...` / `just <recipe> ...`, used in delegate mode
+    pub program: Vec<String>,
+    pub working_dir: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    pub timeouts: Timeouts,
+    // run inside a pseudo-terminal so tools keep their colors
+    pub pty: bool,
+    // program and arguments that run an inline recipe line, `sh -c` if unset
+    pub shell: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RunStatus {
+    Exited(i32),
+    Cancelled,
+    TimedOut(Duration),
+}
+
+impl RunStatus {
+    pub fn is_success(self) -> bool {
+        self == RunStatus::Exited(0)
+    }
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunStatus::Exited(code) => write!(f, "exit: {code}"),
+            RunStatus::Cancelled => write!(f, "cancelled"),
+            RunStatus::TimedOut(limit) => write!(f, "timed out after {}", format_duration(*limit)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+    // headers and status lines written by lazymake itself
+    Info,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputLine {
+    pub stream: Stream,
+    pub time: DateTime<Local>,
+    pub text: String,
+}
+
+impl OutputLine {
+    pub fn new(stream: Stream, text: String) -> Self {
+        Self {
+            stream,
+            time: Local::now(),
+            text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunEvent {
+    Line(OutputLine),
+    Finished(RunStatus),
+}
+
+// a run started by `Executor::start`; dropping it kills whatever is running
+pub struct RunHandle {
+    pub events: UnboundedReceiver<RunEvent>,
+    cancel: watch::Sender<bool>,
+}
+
+impl RunHandle {
+    pub fn cancel(&self) {
+        let _ = self.cancel.send(true);
+    }
+}
+
+impl Drop for RunHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// how long a cancelled task gets to exit after SIGINT, and then after
+// SIGTERM, before its process group is killed
+#[derive(Debug, Clone, Copy)]
+pub struct GracePeriods {
+    pub interrupt: Duration,
+    pub terminate: Duration,
+}
+
+impl Default for GracePeriods {
+    fn default() -> Self {
+        Self {
+            interrupt: Duration::from_secs(2),
+            terminate: Duration::from_secs(3),
+        }
+    }
+}
+
+impl GracePeriods {
+    // `LAZYMAKE_SIGINT_GRACE` / `LAZYMAKE_SIGTERM_GRACE`, in seconds, win
+    // over the configured periods; bad values are reported and ignored
+    pub fn new(interrupt: Option<Duration>, terminate: Option<Duration>) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+        let mut seconds = |name: &str| {
+            let value = std::env::var(name).ok()?;
+            let parsed = value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(|s| Duration::try_from_secs_f64(s).ok());
+            if parsed.is_none() {
+                errors.push(format!("{name}: `{value}` is not a number of seconds (ignored)"));
+            }
+            parsed
+        };
+        let defaults = Self::default();
+        let grace = Self {
+            interrupt: seconds("LAZYMAKE_SIGINT_GRACE")
+                .or(interrupt)
+                .unwrap_or(defaults.interrupt),
+            terminate: seconds("LAZYMAKE_SIGTERM_GRACE")
+                .or(terminate)
+                .unwrap_or(defaults.terminate),
+        };
+        (grace, errors)
+    }
+}
+
+// what a running step needs besides its command
+struct RunContext {
+    tx: UnboundedSender<RunEvent>,
+    cancel: watch::Receiver<bool>,
+    grace: GracePeriods,
+}
+
+impl RunContext {
+    // a handle that is gone can no longer ask, so that counts as a cancel
+    fn is_cancelled(&self) -> bool {
+        *self.cancel.borrow() || self.cancel.has_changed().is_err()
+    }
+}
+
+struct Step {
+    display: String,
+    command: Command,
+    ignore_errors: bool,
+}
+
+#[derive(Clone)]
 pub struct Executor {
     working_dir: PathBuf,
+    grace: GracePeriods,
 }
 
 impl Executor {
-    pub fn new(working_dir: PathBuf) -> Self {
-        Self { working_dir }
+    pub fn new(working_dir: PathBuf, grace: GracePeriods) -> Self {
+        Self { working_dir, grace }
     }
 
-    pub async fn execute_task(&self, task_name: &str, commands: &[String]) -> Result<(i32, String)> {
-        let mut output = String::new();
-        let mut exit_code = 0;
-        let start_time = std::time::Instant::now();
+    // runs the invocations in the background; output arrives line by line
+    // on the returned handle, followed by a single `Finished` event
+    pub fn start(&self, invocations: Vec<Invocation>) -> RunHandle {
+        let (tx, events) = mpsc::unbounded_channel();
+        let (cancel, cancel_rx) = watch::channel(false);
+        let executor = self.clone();
+        let mut ctx = RunContext {
+            tx,
+            cancel: cancel_rx,
+            grace: self.grace,
+        };
+        tokio::spawn(async move {
+            let status = executor.execute_plan(&invocations, &mut ctx).await;
+            let _ = ctx.tx.send(RunEvent::Finished(status));
+        });
+        RunHandle { events, cancel }
+    }
 
-        output.push_str(&format!("executing task: {}\n", task_name));
-        output.push_str(&format!("working directory: {}\n", self.working_dir.display()));
-        output.push_str(&"─".repeat(60));
-        output.push('\n');
-
-        for cmd in commands {
-            let output_result = if cfg!(target_os = "windows") {
-                Command::new("cmd")
-                    .args(&["/C", cmd])
-                    .current_dir(&self.working_dir)
-                    .output()
-            } else {
-                Command::new("sh")
-                    .arg("-c")
-                    .arg(cmd)
-                    .current_dir(&self.working_dir)
-                    .output()
-            };
+    // runs each invocation in order and stops at the first failure
+    async fn execute_plan(&self, invocations: &[Invocation], ctx: &mut RunContext) -> RunStatus {
+        if let [invocation] = invocations {
+            return self.execute_task(invocation, ctx).await;
+        }
 
-            match output_result {
-                Ok(result) => {
-                    output.push_str(&format!("$ {}\n", cmd));
-                    output.push_str(&String::from_utf8_lossy(&result.stdout));
-                    if !result.stderr.is_empty() {
-                        output.push_str(&String::from_utf8_lossy(&result.stderr));
-                    }
-                    exit_code = result.status.code().unwrap_or(1);
-                    if exit_code != 0 {
-                        output.push_str(&format!("\nerror: command failed with exit code {}\n", exit_code));
-                        break;
+        let mut summary = Vec::new();
+        let mut status = RunStatus::Exited(0);
+
+        for (idx, invocation) in invocations.iter().enumerate() {
+            let step = format!("[{}/{}] {}", idx + 1, invocations.len(), invocation.task_name);
+            if !status.is_success() {
+                summary.push(format!("  - {} (skipped)", step));
+                continue;
+            }
+
+            send(&ctx.tx, step.clone());
+            let start_time = std::time::Instant::now();
+            status = self.execute_task(invocation, ctx).await;
+            send(&ctx.tx, String::new());
+
+            let elapsed = start_time.elapsed().as_secs_f64();
+            summary.push(match status {
+                RunStatus::Exited(0) => format!("  ✓ {} ({:.2}s)", step, elapsed),
+                RunStatus::Exited(code) => format!("  ✗ {} (exit code {})", step, code),
+                RunStatus::Cancelled | RunStatus::TimedOut(_) => format!("  ✗ {} ({})", step, status),
+            });
+        }
+
+        send(&ctx.tx, "steps:".to_string());
+        for line in summary {
+            send(&ctx.tx, line);
+        }
+        status
+    }
+
+    // runs the invocations on the real terminal so they can prompt for
+    // input; the caller has to leave raw mode and the alternate screen first
+    pub async fn run_attached(&self, invocations: &[Invocation]) -> RunStatus {
+        for invocation in invocations {
+            let working_dir = self.task_dir(invocation);
+            println!("executing task: {}", invocation.task_name);
+            println!("working directory: {}", working_dir.display());
+            println!("{}", "─".repeat(60));
+
+            for mut step in self.steps(invocation, working_dir) {
+                println!("$ {}", step.display);
+                let child = step
+                    .command
+                    .stdin(Stdio::inherit())
+                    .stdout(Stdio::inherit())
+                    .stderr(Stdio::inherit())
+                    .spawn();
+                let code = match child {
+                    Ok(mut child) => loop {
+                        // Ctrl+C reaches the task through the terminal; it
+                        // must not take lazymake down with it
+                        tokio::select! {
+                            status = child.wait() => break status.map_or(1, |s| s.code().unwrap_or(1)),
+                            _ = tokio::signal::ctrl_c() => {}
+                        }
+                    },
+                    Err(e) => {
+                        println!("error executing command: {}", e);
+                        1
                     }
+                };
+
+                if code != 0 && step.ignore_errors {
+                    println!("(ignored exit code {})", code);
+                } else if code != 0 {
+                    return RunStatus::Exited(code);
+                }
+            }
+            println!();
+        }
+        RunStatus::Exited(0)
+    }
+
+    // delegated runs always start from the project root, where make/just
+    // find the root file; module recipes change directory themselves
+    fn task_dir<'a>(&'a self, invocation: &'a Invocation) -> &'a Path {
+        match invocation.mode {
+            ExecutionMode::Delegate => &self.working_dir,
+            ExecutionMode::Inline => invocation.working_dir.as_ref().unwrap_or(&self.working_dir),
+        }
+    }
+
+    async fn execute_task(&self, invocation: &Invocation, ctx: &mut RunContext) -> RunStatus {
+        let working_dir = self.task_dir(invocation);
+        let mut status = RunStatus::Exited(0);
+        let start_time = std::time::Instant::now();
+        let tx = ctx.tx.clone();
+        let task_deadline = deadline(invocation.timeouts.task);
+
+        send(&tx, format!("executing task: {}", invocation.task_name));
+        send(&tx, format!("working directory: {}", working_dir.display()));
+        let capture = if invocation.pty { " (pty)" } else { "" };
+        send(&tx, format!("mode: {}{}", invocation.mode.label(), capture));
+        if let Some(limit) = invocation.timeouts.task {
+            send(&tx, format!("timeout: {}", format_duration(limit)));
+        }
+        send(&tx, "─".repeat(60));
+
+        for mut step in self.steps(invocation, working_dir) {
+            if ctx.is_cancelled() {
+                status = RunStatus::Cancelled;
+                break;
+            }
+            if let Some((_, limit)) = task_deadline.filter(|(at, _)| Instant::now() >= *at) {
+                status = RunStatus::TimedOut(limit);
+                break;
+            }
+            send(&tx, format!("$ {}", step.display));
+            let command_deadline = deadline(invocation.timeouts.command);
+            // whichever limit runs out first
+            let deadline = match (task_deadline, command_deadline) {
+                (Some(task), Some(command)) => Some(if command.0 < task.0 { command } else { task }),
+                (task, command) => task.or(command),
+            };
+            match run_step(&mut step.command, invocation.pty, deadline, ctx).await {
+                Ok(RunStatus::Exited(code)) if code != 0 && step.ignore_errors => {
+                    send(&tx, format!("(ignored exit code {})", code));
+                }
+                Ok(RunStatus::Exited(0)) => {}
+                Ok(RunStatus::Exited(code)) => {
+                    status = RunStatus::Exited(code);
+                    send(&tx, String::new());
+                    send(&tx, format!("error: command failed with exit code {}", code));
+                    break;
+                }
+                Ok(stopped @ (RunStatus::Cancelled | RunStatus::TimedOut(_))) => {
+                    status = stopped;
+                    break;
                 }
                 Err(e) => {
-                    output.push_str(&format!("error executing command: {}\n", e));
-                    exit_code = 1;
+                    send(&tx, format!("error executing command: {}", e));
+                    status = RunStatus::Exited(1);
                     break;
                 }
             }
         }
 
         let elapsed = start_time.elapsed();
-        output.push_str(&"─".repeat(60));
-        output.push('\n');
-        output.push_str(&format!("exit code: {}\n", exit_code));
-        output.push_str(&format!("execution time: {:.2}s\n", elapsed.as_secs_f64()));
+        send(&tx, "─".repeat(60));
+        match status {
+            RunStatus::Exited(code) => send(&tx, format!("exit code: {}", code)),
+            stopped => send(&tx, stopped.to_string()),
+        }
+        send(&tx, format!("execution time: {:.2}s", elapsed.as_secs_f64()));
+        status
+    }
+
+    fn steps(&self, invocation: &Invocation, working_dir: &Path) -> Vec<Step> {
+        match invocation.mode {
+            ExecutionMode::Delegate => {
+                let Some((program, args)) = invocation.program.split_first() else {
+                    return Vec::new();
+                };
+                let mut command = Command::new(program);
+                command.args(args).current_dir(working_dir).stdin(Stdio::null());
+                vec![Step {
+                    display: invocation.program.join(" "),
+                    command,
+                    ignore_errors: false,
+                }]
+            }
+            ExecutionMode::Inline => invocation
+                .commands
+                .iter()
+                .map(|cmd| {
+                    let (cmd, ignore_errors) = strip_recipe_prefixes(cmd);
+                    let mut command = if let Some((program, args)) =
+                        invocation.shell.as_deref().and_then(|shell| shell.split_first())
+                    {
+                        let mut command = Command::new(program);
+                        command.args(args).arg(cmd);
+                        command
+                    } else if cfg!(target_os = "windows") {
+                        let mut command = Command::new("cmd");
+                        command.args(["/C", cmd]);
+                        command
+                    } else {
+                        let mut command = Command::new("sh");
+                        command.arg("-c").arg(cmd);
+                        command
+                    };
+                    command
+                        .current_dir(working_dir)
+                        .envs(invocation.env.iter().cloned())
+                        .stdin(Stdio::null());
+                    Step {
+                        display: cmd.to_string(),
+                        command,
+                        ignore_errors,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+// `@` (silent), `-` (ignore errors) and `+` (always run) recipe line prefixes
+// are understood by make and just, not by the shell
+fn strip_recipe_prefixes(cmd: &str) -> (&str, bool) {
+    let mut ignore_errors = false;
+    let mut cmd = cmd.trim_start();
+    while let Some(c) = cmd.chars().next().filter(|c| matches!(c, '@' | '-' | '+')) {
+        ignore_errors |= c == '-';
+        cmd = cmd[1..].trim_start();
+    }
+    (cmd, ignore_errors)
+}
+
+fn send(tx: &UnboundedSender<RunEvent>, line: String) {
+    let _ = tx.send(RunEvent::Line(OutputLine::new(Stream::Info, line)));
+}
+
+type Output = Box<dyn AsyncRead + Unpin + Send>;
+
+// how long output is still read after a step has exited
+const PIPE_DRAIN: Duration = Duration::from_millis(250);
+
+async fn run_step(
+    command: &mut Command,
+    use_pty: bool,
+    deadline: Option<(Instant, Duration)>,
+    ctx: &mut RunContext,
+) -> std::io::Result<RunStatus> {
+    // its own process group, so a cancel reaches everything the step spawned
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let terminal = if use_pty {
+        let (stdout, stderr, master) = open_terminal()?;
+        command.stdout(stdout).stderr(stderr);
+        Some(master)
+    } else {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        None
+    };
+    let spawned = command.kill_on_drop(true).spawn();
+    // drop the command's copies of the terminal, or it never reports end of file
+    command.stdout(Stdio::null()).stderr(Stdio::null());
+    let mut child = spawned?;
+
+    let pid = child.id();
+    // a terminal merges both streams into one
+    let (stdout, stderr): (Option<Output>, Option<Output>) = match terminal {
+        Some(master) => (Some(Box::new(master)), None),
+        None => (
+            child.stdout.take().map(|out| Box::new(out) as Output),
+            child.stderr.take().map(|err| Box::new(err) as Output),
+        ),
+    };
+    let RunContext { tx, cancel, grace } = ctx;
+    let wait = async {
+        tokio::select! {
+            status = child.wait() => Ok(RunStatus::Exited(status?.code().unwrap_or(1))),
+            _ = cancelled(cancel) => {
+                terminate(&mut child, pid, *grace, tx).await;
+                Ok(RunStatus::Cancelled)
+            }
+            limit = expired(deadline) => {
+                terminate(&mut child, pid, *grace, tx).await;
+                Ok(RunStatus::TimedOut(limit))
+            }
+        }
+    };
+    let reading = async {
+        tokio::join!(
+            forward_lines(stdout, Stream::Stdout, tx),
+            forward_lines(stderr, Stream::Stderr, tx)
+        )
+    };
+    tokio::pin!(wait, reading);
+    let mut read_all = false;
+    let status = loop {
+        tokio::select! {
+            status = &mut wait => break status,
+            _ = &mut reading, if !read_all => read_all = true,
+        }
+    };
+    // something the step started in the background can hold the pipes open
+    // long after the step itself is done
+    if !read_all && tokio::time::timeout(PIPE_DRAIN, reading).await.is_err() {
+        send(tx, "stopped reading output left open by background processes".to_string());
+    }
+    status
+}
+
+// the slave side twice, for stdout and stderr, and the master to read from
+#[cfg(unix)]
+fn open_terminal() -> std::io::Result<(Stdio, Stdio, tokio::fs::File)> {
+    let (cols, rows) = crossterm::terminal::size().unwrap_or((120, 40));
+    let pty = pty::open(cols, rows)?;
+    let stderr = pty.slave.try_clone()?;
+    Ok((
+        Stdio::from(pty.slave),
+        Stdio::from(stderr),
+        tokio::fs::File::from_std(pty.master),
+    ))
+}
+
+#[cfg(not(unix))]
+fn open_terminal() -> std::io::Result<(Stdio, Stdio, tokio::fs::File)> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "pty mode is only available on unix",
+    ))
+}
+
+// resolves once a cancel is requested or the handle is gone, so an
+// abandoned run still goes through the interrupt, terminate, kill steps
+async fn cancelled(cancel: &mut watch::Receiver<bool>) {
+    let _ = cancel.wait_for(|&cancelled| cancelled).await;
+}
 
-        Ok((exit_code, output))
+// when a limit that starts now runs out; one too far away to represent is
+// no limit at all
+fn deadline(limit: Option<Duration>) -> Option<(Instant, Duration)> {
+    let limit = limit?;
+    Some((Instant::now().checked_add(limit)?, limit))
+}
+
+// resolves with the limit once the deadline passes, or never without one
+async fn expired(deadline: Option<(Instant, Duration)>) -> Duration {
+    match deadline {
+        Some((at, limit)) => {
+            tokio::time::sleep_until(at).await;
+            limit
+        }
+        None => std::future::pending().await,
+    }
+}
+
+// SIGINT first so the task can clean up like it would on Ctrl+C, then
+// SIGTERM, then SIGKILL for anything that ignored both
+async fn terminate(child: &mut Child, pid: Option<u32>, grace: GracePeriods, tx: &UnboundedSender<RunEvent>) {
+    #[cfg(unix)]
+    {
+        let stages = [
+            (libc::SIGINT, "SIGINT", grace.interrupt),
+            (libc::SIGTERM, "SIGTERM", grace.terminate),
+        ];
+        for (signal, name, wait) in stages {
+            send(tx, format!("sending {} to process group", name));
+            signal_group(pid, signal);
+            if tokio::time::timeout(wait, child.wait()).await.is_ok() {
+                return;
+            }
+            send(tx, format!("still running after {:.1}s", wait.as_secs_f64()));
+        }
+        send(tx, "sending SIGKILL to process group".to_string());
+        signal_group(pid, libc::SIGKILL);
+    }
+    #[cfg(not(unix))]
+    let _ = (pid, grace, tx);
+
+    let _ = child.start_kill();
+    let _ = child.wait().await;
+}
+
+#[cfg(unix)]
+fn signal_group(pid: Option<u32>, signal: libc::c_int) {
+    if let Some(pid) = pid {
+        // SAFETY: killpg only sends a signal; the group was created for this
+        // step by `process_group(0)`, so its id is the leader's pid
+        unsafe {
+            libc::killpg(pid as libc::pid_t, signal);
+        }
+    }
+}
+
+// lines from stdout and stderr are sent as they arrive, so their
+// interleaving is kept
+async fn forward_lines<R: AsyncRead + Unpin>(reader: Option<R>, stream: Stream, tx: &UnboundedSender<RunEvent>) {
+    let Some(reader) = reader else {
+        return;
+    };
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    while let Ok(n) = reader.read_until(b'\n', &mut buf).await {
+        if n == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let text = line.trim_end_matches(['\n', '\r']).to_string();
+        let _ = tx.send(RunEvent::Line(OutputLine::new(stream, text)));
+        buf.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadlines_too_far_away_are_no_limit() {
+        assert_eq!(deadline(None), None);
+        assert_eq!(deadline(Some(Duration::MAX)), None);
+        let (_, limit) = deadline(Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(limit, Duration::from_secs(5));
+    }
+
+    // the only test that touches these variables, so it can set them
+    #[test]
+    fn grace_periods_from_the_environment_win_and_bad_ones_are_reported() {
+        std::env::set_var("LAZYMAKE_SIGINT_GRACE", "1e30");
+        std::env::set_var("LAZYMAKE_SIGTERM_GRACE", "0.5");
+        let (grace, errors) = GracePeriods::new(Some(Duration::from_secs(7)), Some(Duration::from_secs(9)));
+        std::env::remove_var("LAZYMAKE_SIGINT_GRACE");
+        std::env::remove_var("LAZYMAKE_SIGTERM_GRACE");
+        assert_eq!(grace.interrupt, Duration::from_secs(7));
+        assert_eq!(grace.terminate, Duration::from_millis(500));
+        assert_eq!(errors, ["LAZYMAKE_SIGINT_GRACE: `1e30` is not a number of seconds (ignored)"]);
+
+        let (grace, errors) = GracePeriods::new(None, None);
+        assert_eq!(grace.interrupt, Duration::from_secs(2));
+        assert_eq!(grace.terminate, Duration::from_secs(3));
+        assert!(errors.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn background_processes_do_not_hold_the_step_open() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (_cancel, cancel) = watch::channel(false);
+        let mut ctx = RunContext {
+            tx,
+            cancel,
+            grace: GracePeriods::default(),
+        };
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("sleep 5 & echo started");
+        let started = Instant::now();
+        let status = run_step(&mut command, false, None, &mut ctx).await.unwrap();
+        assert!(matches!(status, RunStatus::Exited(0)));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        let mut lines = Vec::new();
+        while let Ok(RunEvent::Line(line)) = rx.try_recv() {
+            lines.push(line.text);
+        }
+        assert_eq!(lines[0], "started");
     }
 }
@@ -0,0 +1,54 @@
+use std::fs::File;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::ptr;
+
+// a pseudo-terminal pair; the step writes to `slave`, lazymake reads `master`
+pub struct Pty {
+    pub master: File,
+    pub slave: OwnedFd,
+}
+
+pub fn open(cols: u16, rows: u16) -> io::Result<Pty> {
+    let mut master = -1;
+    let mut slave = -1;
+    let size = libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+
+    // SAFETY: both out-pointers and the winsize are valid for the call, and
+    // a null termios asks for the default line discipline
+    let rc = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &size,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: openpty succeeded, so both descriptors are open and owned by us
+    let (master, slave) = unsafe { (File::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+    // the step only gets the slave through its stdio, so neither may leak
+    // into it otherwise; a stray copy of the slave would keep the master
+    // from ever reaching end of file
+    set_cloexec(&master)?;
+    set_cloexec(&slave)?;
+    Ok(Pty { master, slave })
+}
+
+fn set_cloexec(fd: &impl AsRawFd) -> io::Result<()> {
+    // SAFETY: fcntl on a descriptor we own
+    let rc = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+    if rc == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,430 @@
+use crate::config::xdg_dir;
+use crate::executor::{OutputLine, RunStatus};
+use crate::params::Arguments;
+use crate::parser::Task;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const HISTORY_FILE: &str = "history.jsonl";
+const LOG_DIR: &str = "logs";
+// older runs are forgotten along with their logs
+const MAX_ENTRIES: usize = 500;
+// how many recent runs the running-time estimate looks at
+const ESTIMATE_RUNS: usize = 10;
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub task_name: String,
+    #[serde(default)]
+    pub arguments: SavedArguments,
+    pub started: DateTime<Local>,
+    pub finished: DateTime<Local>,
+    pub duration: Duration,
+    pub status: RunStatus,
+    // file name in the project's log directory
+    pub log: Option<String>,
+}
+
+// the arguments of a run without the parsed recipe parameters, which may
+// have changed by the time the run is looked at again
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedArguments {
+    pub positional: Vec<(String, Vec<String>)>,
+    pub overrides: Vec<(String, String)>,
+    pub timeout: Option<Duration>,
+}
+
+impl From<&Arguments> for SavedArguments {
+    fn from(arguments: &Arguments) -> Self {
+        Self {
+            positional: arguments
+                .positional
+                .iter()
+                .map(|(param, values)| (param.name.clone(), values.clone()))
+                .collect(),
+            overrides: arguments.overrides.clone(),
+            timeout: arguments.timeout,
+        }
+    }
+}
+
+impl SavedArguments {
+    // parameters are matched by name; values of parameters the recipe no
+    // longer has are dropped
+    pub fn to_arguments(&self, task: &Task) -> Arguments {
+        let positional = task
+            .parameters
+            .iter()
+            .filter_map(|param| {
+                let (_, values) = self.positional.iter().find(|(name, _)| *name == param.name)?;
+                Some((param.clone(), values.clone()))
+            })
+            .collect();
+        Arguments {
+            positional,
+            overrides: self.overrides.clone(),
+            timeout: self.timeout,
+        }
+    }
+}
+
+// `name=value ... NAME=value`, as the run would be typed
+impl fmt::Display for SavedArguments {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let positional = self
+            .positional
+            .iter()
+            .map(|(name, values)| format!("{name}={}", values.join(" ")));
+        let overrides = self.overrides.iter().map(|(k, v)| format!("{k}={v}"));
+        let parts: Vec<String> = positional.chain(overrides).collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DurationStats {
+    pub runs: usize,
+    pub last: Duration,
+    pub average: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+}
+
+impl DurationStats {
+    pub fn new(samples: &[Duration]) -> Option<Self> {
+        let last = *samples.last()?;
+        Some(Self {
+            runs: samples.len(),
+            last,
+            average: samples.iter().sum::<Duration>() / samples.len() as u32,
+            p50: percentile(samples, 50),
+            p95: percentile(samples, 95),
+        })
+    }
+}
+
+// nearest-rank percentile
+fn percentile(samples: &[Duration], p: usize) -> Duration {
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+// the last `width` samples as bars against the slowest of them, so jitter
+// stays flat and a run that took twice as long stands out
+pub fn sparkline(samples: &[Duration], width: usize) -> String {
+    let recent = &samples[samples.len().saturating_sub(width)..];
+    let Some(max) = recent.iter().max().filter(|max| !max.is_zero()) else {
+        return String::new();
+    };
+    let top = (SPARK_LEVELS.len() - 1) as f64;
+    recent
+        .iter()
+        .map(|sample| SPARK_LEVELS[(sample.as_secs_f64() / max.as_secs_f64() * top).round() as usize])
+        .collect()
+}
+
+// runs of one project, kept in `$XDG_STATE_HOME/lazymake/<project>/`
+pub struct History {
+    // `None` when there is nowhere to write, history then lasts one session
+    dir: Option<PathBuf>,
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    // an unreadable history is started afresh; entries that no longer parse
+    // are skipped
+    pub fn load(project_dir: &Path) -> Self {
+        let dir = xdg_dir("XDG_STATE_HOME", ".local/state").map(|state| state.join(project_key(project_dir)));
+        Self::open(dir)
+    }
+
+    fn open(dir: Option<PathBuf>) -> Self {
+        let entries = dir
+            .as_ref()
+            .and_then(|dir| File::open(dir.join(HISTORY_FILE)).ok())
+            .map(|file| {
+                BufReader::new(file)
+                    .lines()
+                    .map_while(|line| line.ok())
+                    .filter_map(|line| serde_json::from_str(&line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { dir, entries }
+    }
+
+    // durations of the task's successful runs, oldest first; failures often
+    // stop early and would drag the numbers down
+    pub fn durations(&self, task_name: &str) -> Vec<Duration> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.task_name == task_name && entry.status.is_success())
+            .map(|entry| entry.duration)
+            .collect()
+    }
+
+    // the median of the latest successful runs
+    pub fn estimate(&self, task_name: &str) -> Option<Duration> {
+        let durations = self.durations(task_name);
+        let recent = &durations[durations.len().saturating_sub(ESTIMATE_RUNS)..];
+        (!recent.is_empty()).then(|| percentile(recent, 50))
+    }
+
+    pub fn log_path(&self, entry: &HistoryEntry) -> Option<PathBuf> {
+        Some(self.dir.as_ref()?.join(LOG_DIR).join(entry.log.as_ref()?))
+    }
+
+    // saves the output as the entry's log and appends the entry
+    pub fn record(&mut self, entry: HistoryEntry, output: &[OutputLine]) -> Result<()> {
+        let mut log = self.start_log(&entry.task_name, entry.started)?;
+        if let Some(log) = &mut log {
+            for line in output {
+                log.write(line)?;
+            }
+        }
+        self.finish(entry, log)
+    }
+
+    // a log for a run that is written as the output arrives; `None` when
+    // there is nowhere to write
+    pub fn start_log(&self, task_name: &str, started: DateTime<Local>) -> Result<Option<RunLog>> {
+        let Some(dir) = &self.dir else {
+            return Ok(None);
+        };
+        let logs = dir.join(LOG_DIR);
+        fs::create_dir_all(&logs)
+            .map_err(|e| anyhow!("cannot create {}: {e}", logs.display()))?;
+        let name = format!(
+            "{}-{}.jsonl",
+            started.format("%Y%m%d-%H%M%S%.3f"),
+            task_name.replace(|c: char| !c.is_alphanumeric() && c != '-' && c != '_', "_")
+        );
+        let path = logs.join(&name);
+        let file = File::create(&path).map_err(|e| anyhow!("cannot write {}: {e}", path.display()))?;
+        Ok(Some(RunLog {
+            name,
+            path,
+            file: BufWriter::new(file),
+        }))
+    }
+
+    // appends the entry along with the log the run was written to
+    pub fn finish(&mut self, mut entry: HistoryEntry, log: Option<RunLog>) -> Result<()> {
+        let Some(dir) = self.dir.clone() else {
+            self.push(entry);
+            return Ok(());
+        };
+        if let Some(mut log) = log {
+            log.file
+                .flush()
+                .map_err(|e| anyhow!("cannot write {}: {e}", log.path.display()))?;
+            entry.log = Some(log.name);
+        }
+
+        let path = dir.join(HISTORY_FILE);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| anyhow!("cannot write {}: {e}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+
+        if self.push(entry) {
+            self.rewrite()?;
+        }
+        Ok(())
+    }
+
+    pub fn read_log(&self, entry: &HistoryEntry) -> Result<Vec<OutputLine>> {
+        let path = self
+            .log_path(entry)
+            .ok_or_else(|| anyhow!("no log was saved for this run"))?;
+        let file = File::open(&path).map_err(|e| anyhow!("cannot read {}: {e}", path.display()))?;
+        BufReader::new(file)
+            .lines()
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+
+    // returns whether old entries had to be dropped
+    fn push(&mut self, entry: HistoryEntry) -> bool {
+        self.entries.push(entry);
+        if self.entries.len() <= MAX_ENTRIES {
+            return false;
+        }
+        let dropped: Vec<HistoryEntry> = self.entries.drain(..self.entries.len() - MAX_ENTRIES).collect();
+        if let Some(dir) = &self.dir {
+            for log in dropped.iter().filter_map(|entry| entry.log.as_ref()) {
+                let _ = fs::remove_file(dir.join(LOG_DIR).join(log));
+            }
+        }
+        true
+    }
+
+    // replaces the history file with the entries still kept
+    fn rewrite(&self) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let path = dir.join(HISTORY_FILE);
+        let temp = path.with_extension("jsonl.tmp");
+        let mut file = BufWriter::new(File::create(&temp)?);
+        for entry in &self.entries {
+            writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        }
+        file.flush()?;
+        fs::rename(&temp, &path)?;
+        Ok(())
+    }
+}
+
+// the full output of a run in the project's log directory, one json line
+// per output line
+pub struct RunLog {
+    name: String,
+    path: PathBuf,
+    file: BufWriter<File>,
+}
+
+impl RunLog {
+    pub fn write(&mut self, line: &OutputLine) -> Result<()> {
+        writeln!(self.file, "{}", serde_json::to_string(line)?)
+            .map_err(|e| anyhow!("cannot write {}: {e}", self.path.display()))
+    }
+}
+
+// the project path flattened into one directory name by percent-encoding
+// the separators, `/src/app` becomes `%2Fsrc%2Fapp`
+fn project_key(project_dir: &Path) -> String {
+    let path = project_dir
+        .canonicalize()
+        .unwrap_or_else(|_| project_dir.to_path_buf());
+    let mut key = String::new();
+    for c in path.to_string_lossy().chars() {
+        match c {
+            '%' | '/' | '\\' | ':' => key.push_str(&format!("%{:02X}", c as u32)),
+            c => key.push(c),
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::Stream;
+
+    fn secs(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&s| Duration::from_secs(s)).collect()
+    }
+
+    // a history in a directory of the test's own
+    fn history_in(test: &str) -> (History, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("lazymake-history-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        (History::open(Some(dir.clone())), dir)
+    }
+
+    fn entry(task_name: &str, started: DateTime<Local>, status: RunStatus) -> HistoryEntry {
+        HistoryEntry {
+            task_name: task_name.to_string(),
+            arguments: SavedArguments::default(),
+            started,
+            finished: started,
+            duration: Duration::from_secs(1),
+            status,
+            log: None,
+        }
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let samples = secs(&[9, 1, 8, 2, 7, 3, 6, 4, 5, 10]);
+        assert_eq!(percentile(&samples, 50), Duration::from_secs(5));
+        assert_eq!(percentile(&samples, 95), Duration::from_secs(10));
+        assert_eq!(percentile(&secs(&[4]), 50), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn duration_stats() {
+        assert_eq!(DurationStats::new(&[]), None);
+        let stats = DurationStats::new(&secs(&[2, 4, 9])).unwrap();
+        assert_eq!(stats.runs, 3);
+        assert_eq!(stats.last, Duration::from_secs(9));
+        assert_eq!(stats.average, Duration::from_secs(5));
+        assert_eq!(stats.p50, Duration::from_secs(4));
+        assert_eq!(stats.p95, Duration::from_secs(9));
+    }
+
+    #[test]
+    fn sparklines_scale_to_the_slowest_recent_run() {
+        assert_eq!(sparkline(&secs(&[1, 2, 4]), 8), "▃▅█");
+        assert_eq!(sparkline(&secs(&[100, 2, 4]), 2), "▅█");
+        assert_eq!(sparkline(&secs(&[0, 0]), 8), "");
+        assert_eq!(sparkline(&[], 8), "");
+    }
+
+    #[test]
+    fn project_keys_are_unambiguous() {
+        assert_eq!(project_key(Path::new("/no/such/app")), "%2Fno%2Fsuch%2Fapp");
+        assert_eq!(project_key(Path::new("/a%/b")), "%2Fa%25%2Fb");
+        assert_ne!(project_key(Path::new("/a%/b")), project_key(Path::new("/a/%b")));
+    }
+
+    #[test]
+    fn entries_and_logs_round_trip() {
+        let (mut history, dir) = history_in("round-trip");
+        let mut run = entry("deploy", Local::now(), RunStatus::TimedOut(Duration::from_secs(30)));
+        run.arguments = SavedArguments {
+            positional: vec![("env".to_string(), vec!["prod".to_string(), "eu".to_string()])],
+            overrides: vec![("CC".to_string(), "clang".to_string())],
+            timeout: Some(Duration::from_secs(30)),
+        };
+        let output = [
+            OutputLine::new(Stream::Stdout, "deploying".to_string()),
+            OutputLine::new(Stream::Stderr, "too slow".to_string()),
+        ];
+        history.record(run.clone(), &output).unwrap();
+
+        let reopened = History::open(Some(dir.clone()));
+        assert_eq!(reopened.entries.len(), 1);
+        let saved = &reopened.entries[0];
+        assert_eq!(saved.task_name, "deploy");
+        assert_eq!(saved.arguments, run.arguments);
+        assert_eq!(saved.status, run.status);
+        assert_eq!(saved.started, run.started);
+        let lines = reopened.read_log(saved).unwrap();
+        let texts: Vec<(Stream, &str)> = lines.iter().map(|l| (l.stream, l.text.as_str())).collect();
+        assert_eq!(texts, [(Stream::Stdout, "deploying"), (Stream::Stderr, "too slow")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn old_entries_are_pruned_with_their_logs() {
+        let (mut history, dir) = history_in("prune");
+        let start = Local::now();
+        for i in 0..=MAX_ENTRIES as i64 {
+            let run = entry(&format!("task{i}"), start + chrono::Duration::seconds(i), RunStatus::Exited(0));
+            history.record(run, &[]).unwrap();
+        }
+        assert_eq!(history.entries.len(), MAX_ENTRIES);
+        assert_eq!(history.entries[0].task_name, "task1");
+        let logs = fs::read_dir(dir.join(LOG_DIR)).unwrap().count();
+        assert_eq!(logs, MAX_ENTRIES);
+
+        let reopened = History::open(Some(dir.clone()));
+        assert_eq!(reopened.entries.len(), MAX_ENTRIES);
+        assert_eq!(reopened.entries[0].task_name, "task1");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,584 @@
+use crate::config::sorted_entries;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use std::fmt;
+
+// everything the task list and history views can be asked to do; `name` is
+// how the action is called in the `[keys]` config table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Filter,
+    Params,
+    Run,
+    RunInteractive,
+    RunBackground,
+    Stop,
+    Restart,
+    Attach,
+    ToggleModule,
+    ToggleMode,
+    TogglePty,
+    ToggleOutput,
+    NextTab,
+    PrevTab,
+    CloseTab,
+    StderrOnly,
+    History,
+    HistoryFilterOutcome,
+    HistoryRerun,
+    Graph,
+    Details,
+    Variables,
+    Cancel,
+    Help,
+    Quit,
+}
+
+impl Action {
+    // in the order the help screen lists them
+    pub const ALL: [Action; 31] = [
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Top,
+        Action::Bottom,
+        Action::Filter,
+        Action::Params,
+        Action::Run,
+        Action::RunInteractive,
+        Action::RunBackground,
+        Action::Stop,
+        Action::Restart,
+        Action::Attach,
+        Action::ToggleModule,
+        Action::ToggleMode,
+        Action::TogglePty,
+        Action::ToggleOutput,
+        Action::NextTab,
+        Action::PrevTab,
+        Action::CloseTab,
+        Action::StderrOnly,
+        Action::History,
+        Action::HistoryFilterOutcome,
+        Action::HistoryRerun,
+        Action::Graph,
+        Action::Details,
+        Action::Variables,
+        Action::Cancel,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::PageUp => "page-up",
+            Action::PageDown => "page-down",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::Filter => "filter",
+            Action::Params => "params",
+            Action::Run => "run",
+            Action::RunInteractive => "run-interactive",
+            Action::RunBackground => "run-background",
+            Action::Stop => "stop",
+            Action::Restart => "restart",
+            Action::Attach => "attach",
+            Action::ToggleModule => "toggle-module",
+            Action::ToggleMode => "toggle-mode",
+            Action::TogglePty => "toggle-pty",
+            Action::ToggleOutput => "toggle-output",
+            Action::NextTab => "next-tab",
+            Action::PrevTab => "prev-tab",
+            Action::CloseTab => "close-tab",
+            Action::StderrOnly => "stderr-only",
+            Action::History => "history",
+            Action::HistoryFilterOutcome => "history-filter-outcome",
+            Action::HistoryRerun => "history-rerun",
+            Action::Graph => "graph",
+            Action::Details => "details",
+            Action::Variables => "variables",
+            Action::Cancel => "cancel",
+            Action::Help => "help",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Up => "Move up",
+            Action::Down => "Move down",
+            Action::PageUp => "Page up (task list or output)",
+            Action::PageDown => "Page down (task list or output)",
+            Action::Top => "Jump to first task",
+            Action::Bottom => "Jump to last task",
+            Action::Filter => "Start filtering (fuzzy search)",
+            Action::Params => "Open the parameter form (arguments, timeout)",
+            Action::Run => "Execute selected task",
+            Action::RunInteractive => "Run the task interactively on the real terminal",
+            Action::RunBackground => "Run the task in the background (servers, watchers)",
+            Action::Stop => "Stop the selected task",
+            Action::Restart => "Restart the selected task",
+            Action::Attach => "Show the output of the selected task's last run",
+            Action::ToggleModule => "Collapse/expand the selected justfile module",
+            Action::ToggleMode => "Toggle running via make/just or inline",
+            Action::TogglePty => "Toggle running inside a pseudo-terminal (keeps colors)",
+            Action::ToggleOutput => "Toggle output panel",
+            Action::NextTab => "Next run tab",
+            Action::PrevTab => "Previous run tab",
+            Action::CloseTab => "Close the finished run tab",
+            Action::StderrOnly => "Show only stderr in the output panel",
+            Action::History => "Browse past runs",
+            Action::HistoryFilterOutcome => "In the history: cycle between all, passed and failed runs",
+            Action::HistoryRerun => "In the history: re-run the selected run with the same parameters",
+            Action::Graph => "Show dependency graph for task",
+            Action::Details => "Show task details and expanded recipe",
+            Action::Variables => "Show makefile variables",
+            Action::Cancel => "Cancel the run in the current tab (quits when idle)",
+            Action::Help => "Show this help",
+            Action::Quit => "Quit (asks first while tasks are running)",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+// one key with its modifiers; shift is part of the character itself, so `G`
+// rather than `shift+g`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match event.code {
+            KeyCode::Char(c) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c)
+            }
+            // terminals send shift+tab as its own key
+            KeyCode::BackTab => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl KeyPress {
+    // `q`, `G`, `enter`, `ctrl+d`, `alt+<`, `shift+tab`, `f5`
+    fn parse(text: &str) -> Option<Self> {
+        let (prefix, key) = match text.strip_suffix("++") {
+            Some(prefix) => (prefix, "+"),
+            None => text.rsplit_once('+').unwrap_or(("", text)),
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in prefix.split('+').filter(|m| !m.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                name => KeyCode::F(name.strip_prefix('f')?.parse().ok()?),
+            },
+        };
+        Some(Self::from(KeyEvent::new(code, modifiers)))
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "shift+tab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+// a key or a chord of keys pressed one after another, `g g`
+pub type Sequence = Vec<KeyPress>;
+
+fn parse_sequence(text: &str) -> Option<Sequence> {
+    let keys: Option<Sequence> = text.split_whitespace().map(KeyPress::parse).collect();
+    keys.filter(|keys| !keys.is_empty())
+}
+
+pub fn format_sequence(keys: &[KeyPress]) -> String {
+    keys.iter().map(|key| key.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+pub const PRESETS: [&str; 3] = ["default", "vim", "emacs"];
+
+fn default_bindings() -> Vec<(Action, &'static [&'static str])> {
+    vec![
+        (Action::Up, &["up"]),
+        (Action::Down, &["down"]),
+        (Action::PageUp, &["pageup"]),
+        (Action::PageDown, &["pagedown"]),
+        (Action::Top, &["home"]),
+        (Action::Bottom, &["end"]),
+        (Action::Filter, &["/"]),
+        (Action::Params, &["p"]),
+        (Action::Run, &["enter"]),
+        (Action::RunInteractive, &["i"]),
+        (Action::RunBackground, &["b"]),
+        (Action::Stop, &["s"]),
+        (Action::Restart, &["R"]),
+        (Action::Attach, &["a"]),
+        (Action::ToggleModule, &["m"]),
+        (Action::ToggleMode, &["x"]),
+        (Action::TogglePty, &["t"]),
+        (Action::ToggleOutput, &["o"]),
+        (Action::NextTab, &["]"]),
+        (Action::PrevTab, &["["]),
+        (Action::CloseTab, &["w"]),
+        (Action::StderrOnly, &["e"]),
+        (Action::History, &["h"]),
+        (Action::HistoryFilterOutcome, &["f"]),
+        (Action::HistoryRerun, &["r"]),
+        (Action::Graph, &["g"]),
+        (Action::Details, &["d"]),
+        (Action::Variables, &["v"]),
+        (Action::Cancel, &["ctrl+c"]),
+        (Action::Help, &["?"]),
+        (Action::Quit, &["q", "esc"]),
+    ]
+}
+
+// what a preset changes on top of the default bindings
+fn preset_bindings(preset: &str) -> Vec<(Action, &'static [&'static str])> {
+    match preset {
+        "vim" => vec![
+            (Action::Up, &["k", "up"]),
+            (Action::Down, &["j", "down"]),
+            (Action::PageUp, &["ctrl+u", "pageup"]),
+            (Action::PageDown, &["ctrl+d", "pagedown"]),
+            (Action::Top, &["g g", "home"]),
+            (Action::Bottom, &["G", "end"]),
+            // `g` starts chords here, so the graph moves to `g d`
+            (Action::Graph, &["g d"]),
+        ],
+        "emacs" => vec![
+            (Action::Up, &["ctrl+p", "up"]),
+            (Action::Down, &["ctrl+n", "down"]),
+            (Action::PageUp, &["alt+v", "pageup"]),
+            (Action::PageDown, &["ctrl+v", "pagedown"]),
+            (Action::Top, &["alt+<", "home"]),
+            (Action::Bottom, &["alt+>", "end"]),
+            (Action::Filter, &["ctrl+s", "/"]),
+            (Action::Cancel, &["ctrl+g", "ctrl+c"]),
+            (Action::Quit, &["ctrl+x ctrl+c", "q"]),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+pub struct Keymap {
+    pub preset: String,
+    // in `Action::ALL` order, with each action's keys as configured
+    bindings: Vec<(Action, Vec<Sequence>)>,
+}
+
+// what a key completes, given the keys pressed before it
+pub enum Resolution {
+    Action(Action),
+    // the keys so far start a chord
+    Pending,
+    Unbound,
+}
+
+impl Keymap {
+    // `preset = "vim"` picks the starting bindings and every other entry
+    // replaces the keys of one action; problems are reported, not fatal
+    pub fn new(keys: &HashMap<String, toml::Value>) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+        let preset = match keys.get("preset") {
+            None => "default".to_string(),
+            Some(toml::Value::String(name)) if PRESETS.contains(&name.as_str()) => name.clone(),
+            Some(value) => {
+                errors.push(format!(
+                    "keys.preset: {value} is not a preset, expected one of {}",
+                    PRESETS.join(", ")
+                ));
+                "default".to_string()
+            }
+        };
+
+        let mut bindings: HashMap<Action, Vec<Sequence>> = HashMap::new();
+        for (action, keys) in default_bindings().into_iter().chain(preset_bindings(&preset)) {
+            let sequences = keys.iter().filter_map(|k| parse_sequence(k)).collect();
+            bindings.insert(action, sequences);
+        }
+
+        for (name, value) in sorted_entries(keys) {
+            if name == "preset" {
+                continue;
+            }
+            let Some(action) = Action::from_name(name) else {
+                errors.push(format!("keys.{name}: unknown action; `?` lists them all"));
+                continue;
+            };
+            let texts: Vec<&str> = match value {
+                toml::Value::String(text) => vec![text.as_str()],
+                toml::Value::Array(items) => items.iter().filter_map(|item| item.as_str()).collect(),
+                _ => Vec::new(),
+            };
+            let sequences: Option<Vec<Sequence>> = texts.iter().map(|text| parse_sequence(text)).collect();
+            match sequences {
+                Some(sequences) if !sequences.is_empty() => {
+                    // a key moved to this action no longer triggers its old one
+                    for other in bindings.values_mut() {
+                        other.retain(|sequence| !sequences.contains(sequence));
+                    }
+                    bindings.insert(action, sequences);
+                }
+                _ => errors.push(format!(
+                    "keys.{name}: {value} is not a key or list of keys, e.g. \"ctrl+d\" or [\"g g\", \"home\"]"
+                )),
+            }
+        }
+
+        let keymap = Self {
+            preset,
+            bindings: Action::ALL
+                .into_iter()
+                .map(|action| (action, bindings.remove(&action).unwrap_or_default()))
+                .collect(),
+        };
+        errors.extend(keymap.shadowed_chords());
+        (keymap, errors)
+    }
+
+    // a key bound on its own fires at once, so chords starting with it can
+    // never be typed
+    fn shadowed_chords(&self) -> Vec<String> {
+        let all: Vec<(Action, &Sequence)> = self
+            .bindings
+            .iter()
+            .flat_map(|(action, sequences)| sequences.iter().map(move |s| (*action, s)))
+            .collect();
+        let mut errors = Vec::new();
+        for (action, sequence) in &all {
+            for (other, chord) in &all {
+                if chord.len() > sequence.len() && chord.starts_with(sequence) {
+                    errors.push(format!(
+                        "keys: `{}` ({}) hides `{}` ({})",
+                        format_sequence(sequence),
+                        action.name(),
+                        format_sequence(chord),
+                        other.name()
+                    ));
+                }
+            }
+        }
+        errors
+    }
+
+    pub fn resolve(&self, keys: &[KeyPress]) -> Resolution {
+        let mut pending = false;
+        for (action, sequences) in &self.bindings {
+            for sequence in sequences {
+                if sequence.as_slice() == keys {
+                    return Resolution::Action(*action);
+                }
+                pending |= sequence.starts_with(keys);
+            }
+        }
+        if pending {
+            Resolution::Pending
+        } else {
+            Resolution::Unbound
+        }
+    }
+
+    // adds a key to the chord typed so far; a chord that goes nowhere is
+    // dropped and the key is tried on its own
+    pub fn feed(&self, pending: &mut Sequence, key: KeyEvent) -> Option<Action> {
+        let key = KeyPress::from(key);
+        pending.push(key);
+        match self.resolve(pending) {
+            Resolution::Action(action) => {
+                pending.clear();
+                Some(action)
+            }
+            Resolution::Pending => None,
+            Resolution::Unbound if pending.len() > 1 => {
+                pending.clear();
+                self.feed(pending, KeyEvent::new(key.code, key.modifiers))
+            }
+            Resolution::Unbound => {
+                pending.clear();
+                None
+            }
+        }
+    }
+
+    pub fn keys(&self, action: Action) -> &[Sequence] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or(&[], |(_, sequences)| sequences.as_slice())
+    }
+
+    // the first key of an action, for hints
+    pub fn hint(&self, action: Action) -> String {
+        self.keys(action)
+            .first()
+            .map_or_else(|| "unbound".to_string(), |keys| format_sequence(keys))
+    }
+
+    pub fn help_lines(&self) -> Vec<String> {
+        self.bindings
+            .iter()
+            .map(|(action, sequences)| {
+                let keys = if sequences.is_empty() {
+                    "(unbound)".to_string()
+                } else {
+                    sequences.iter().map(|s| format_sequence(s)).collect::<Vec<_>>().join(", ")
+                };
+                format!("{keys:<16} {:<16} {}", action.name(), action.description())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(config: &str) -> (Keymap, Vec<String>) {
+        let keys: HashMap<String, toml::Value> = toml::from_str(config).unwrap();
+        Keymap::new(&keys)
+    }
+
+    fn press(keymap: &Keymap, pending: &mut Sequence, key: &str) -> Option<Action> {
+        let key = KeyPress::parse(key).unwrap();
+        keymap.feed(pending, KeyEvent::new(key.code, key.modifiers))
+    }
+
+    #[test]
+    fn key_names_round_trip() {
+        for text in ["q", "G", "ctrl+d", "alt+<", "shift+tab", "f5", "space", "ctrl++"] {
+            let key = KeyPress::parse(text).unwrap();
+            assert_eq!(KeyPress::parse(&key.to_string()), Some(key), "{text}");
+        }
+        assert_eq!(KeyPress::parse("shift+g"), KeyPress::parse("g"));
+        assert_eq!(KeyPress::parse("hyper+x"), None);
+    }
+
+    #[test]
+    fn chords_wait_for_their_last_key() {
+        let (keymap, errors) = keymap("preset = \"vim\"");
+        assert!(errors.is_empty(), "{errors:?}");
+        let mut pending = Sequence::new();
+        assert_eq!(press(&keymap, &mut pending, "g"), None);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(press(&keymap, &mut pending, "g"), Some(Action::Top));
+        assert!(pending.is_empty());
+        assert_eq!(press(&keymap, &mut pending, "G"), Some(Action::Bottom));
+    }
+
+    #[test]
+    fn a_broken_chord_retries_the_last_key() {
+        let (keymap, _) = keymap("preset = \"emacs\"");
+        let mut pending = Sequence::new();
+        assert_eq!(press(&keymap, &mut pending, "ctrl+x"), None);
+        assert_eq!(press(&keymap, &mut pending, "ctrl+n"), Some(Action::Down));
+        assert!(pending.is_empty());
+        assert_eq!(press(&keymap, &mut pending, "ctrl+x"), None);
+        assert_eq!(press(&keymap, &mut pending, "ctrl+c"), Some(Action::Quit));
+    }
+
+    #[test]
+    fn overrides_take_keys_from_other_actions() {
+        let (keymap, errors) = keymap("run = [\"space\", \"r\"]");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(keymap.hint(Action::Run), "space");
+        assert!(keymap.keys(Action::HistoryRerun).is_empty());
+        assert!(keymap.help_lines().iter().any(|line| line.contains("history-rerun")));
+    }
+
+    #[test]
+    fn shadowed_chords_are_reported() {
+        let (_, errors) = keymap("preset = \"vim\"\nhelp = \"g\"");
+        assert_eq!(
+            errors,
+            ["keys: `g` (help) hides `g g` (top)", "keys: `g` (help) hides `g d` (graph)"]
+        );
+    }
+
+    #[test]
+    fn config_problems_are_reported() {
+        let (keymap, errors) = keymap("preset = \"helix\"\njump = \"j\"\nrun = 5\nup = \"hyper+k\"");
+        assert_eq!(keymap.preset, "default");
+        assert_eq!(errors.len(), 4, "{errors:?}");
+        assert!(errors[0].starts_with("keys.preset:"));
+        assert!(errors[1].starts_with("keys.jump: unknown action"));
+        assert!(errors[2].starts_with("keys.run:"));
+        assert!(errors[3].starts_with("keys.up:"));
+        assert_eq!(keymap.hint(Action::Up), "up");
+    }
+}
@@ -1,16 +1,41 @@
 mod parser;
+mod ansi;
+mod params;
 mod tui;
 mod executor;
 mod app;
+mod config;
+mod history;
+mod keymap;
+mod theme;
+mod cli;
 
 use anyhow::Result;
+use clap::Parser;
+use cli::{Cli, Command};
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let makefile_path = std::env::current_dir()?;
-    
-    let mut app = app::App::new(makefile_path)?;
-    tui::run(&mut app).await?;
-    
-    Ok(())
+async fn main() -> Result<ExitCode> {
+    let cli = Cli::parse();
+    let (dir, source) = cli.source()?;
+
+    let mut app = app::App::new(dir, source)?;
+    match &cli.command {
+        None => tui::run(&mut app).await?,
+        Some(Command::List) => {
+            cli::report_config_errors(&app);
+            cli::list(&app);
+        }
+        Some(Command::Graph { task }) => {
+            cli::report_config_errors(&app);
+            cli::graph(&app, task)?;
+        }
+        Some(Command::Run { task, args, timeout }) => {
+            cli::report_config_errors(&app);
+            return cli::run(&mut app, task, args, *timeout).await;
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,405 @@
+use crate::app::Launch;
+use crate::config::{format_duration, parse_duration, Timeouts};
+use crate::parser::{unquote, FileType, Parameter, Task, Variable, Variadic};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    Required,
+    Optional,
+    Variadic { required: bool },
+    Override,
+    Overrides,
+    Timeout,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParamField {
+    pub label: String,
+    pub value: String,
+    pub hint: String,
+    pub kind: FieldKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParamForm {
+    pub task_name: String,
+    pub fields: Vec<ParamField>,
+    pub focused: usize,
+    pub error: Option<String>,
+    // how the task runs once the form is submitted
+    pub launch: Launch,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Arguments {
+    // positional recipe arguments, in declaration order
+    pub positional: Vec<(Parameter, Vec<String>)>,
+    // `NAME=value` variable overrides
+    pub overrides: Vec<(String, String)>,
+    // replaces the configured task timeout; zero turns it off
+    pub timeout: Option<Duration>,
+}
+
+impl ParamForm {
+    pub fn for_task(task: &Task, variables: &[Variable], timeouts: Timeouts) -> Self {
+        let mut fields: Vec<ParamField> = match task.file_type {
+            FileType::Justfile => task.parameters.iter().map(field_for_parameter).collect(),
+            FileType::Makefile => override_fields(task, variables),
+        };
+        fields.push(ParamField {
+            label: "timeout".to_string(),
+            value: String::new(),
+            hint: match timeouts.task {
+                Some(limit) => format!("configured: {}, 0 for none", format_duration(limit)),
+                None => "e.g. 30s, 5m".to_string(),
+            },
+            kind: FieldKind::Timeout,
+        });
+
+        Self {
+            task_name: task.name.clone(),
+            fields,
+            focused: 0,
+            error: None,
+            launch: Launch::Focused,
+        }
+    }
+
+    pub fn focus_next(&mut self) {
+        if !self.fields.is_empty() {
+            self.focused = (self.focused + 1) % self.fields.len();
+        }
+    }
+
+    pub fn focus_prev(&mut self) {
+        if !self.fields.is_empty() {
+            self.focused = (self.focused + self.fields.len() - 1) % self.fields.len();
+        }
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        if let Some(field) = self.fields.get_mut(self.focused) {
+            if field.value.len() < 200 {
+                field.value.push(c);
+            }
+        }
+        self.error = None;
+    }
+
+    pub fn pop_char(&mut self) {
+        if let Some(field) = self.fields.get_mut(self.focused) {
+            field.value.pop();
+        }
+        self.error = None;
+    }
+
+    pub fn validate(&self, task: &Task) -> Result<Arguments, String> {
+        let mut arguments = Arguments::default();
+        let mut skipped: Option<&str> = None;
+
+        // recipe parameters come first; makefile targets have none and only
+        // get override fields
+        let positional = self.fields.iter().filter(|field| {
+            matches!(
+                field.kind,
+                FieldKind::Required | FieldKind::Optional | FieldKind::Variadic { .. }
+            )
+        });
+        for (field, param) in positional.zip(task.parameters.iter()) {
+            let values: Vec<String> = match field.kind {
+                FieldKind::Variadic { .. } => field
+                    .value
+                    .split_whitespace()
+                    .map(|s| s.to_string())
+                    .collect(),
+                _ if field.value.is_empty() => Vec::new(),
+                _ => vec![field.value.clone()],
+            };
+
+            if values.is_empty() {
+                match field.kind {
+                    FieldKind::Required | FieldKind::Variadic { required: true } => {
+                        return Err(format!("`{}` is required", field.label));
+                    }
+                    _ => {
+                        skipped.get_or_insert(&field.label);
+                        continue;
+                    }
+                }
+            }
+
+            // just binds arguments by position, so a gap cannot be skipped
+            if let Some(empty) = skipped {
+                return Err(format!(
+                    "`{}` must be set when `{}` is given",
+                    empty, field.label
+                ));
+            }
+            arguments.positional.push((param.clone(), values));
+        }
+
+        // picked variables first, so a repeated name in "other" wins
+        for field in &self.fields {
+            if field.kind == FieldKind::Override && !field.value.is_empty() {
+                arguments.overrides.push((field.label.clone(), field.value.clone()));
+            }
+        }
+        if let Some(field) = self.fields.iter().find(|f| f.kind == FieldKind::Overrides) {
+            for word in field.value.split_whitespace() {
+                arguments.overrides.push(parse_override(word)?);
+            }
+        }
+
+        if let Some(field) = self.fields.iter().find(|f| f.kind == FieldKind::Timeout) {
+            if !field.value.trim().is_empty() {
+                arguments.timeout = Some(parse_duration(&field.value)?);
+            }
+        }
+
+        Ok(arguments)
+    }
+}
+
+fn field_for_parameter(param: &Parameter) -> ParamField {
+    let kind = match param.variadic {
+        Some(Variadic::OneOrMore) => FieldKind::Variadic { required: true },
+        Some(Variadic::ZeroOrMore) => FieldKind::Variadic { required: false },
+        None if param.is_required() => FieldKind::Required,
+        None => FieldKind::Optional,
+    };
+
+    // string literal defaults are pre-filled; expressions are only shown
+    let (value, hint) = match param.default.as_deref() {
+        Some(default) => match unquote(default) {
+            Some(literal) => (literal.to_string(), String::new()),
+            None => (String::new(), format!("default: {default}")),
+        },
+        None => (String::new(), String::new()),
+    };
+
+    ParamField {
+        label: param.name.clone(),
+        value,
+        hint,
+        kind,
+    }
+}
+
+// one field per variable the makefile defines, after a free-form field for
+// anything else
+fn override_fields(task: &Task, variables: &[Variable]) -> Vec<ParamField> {
+    let mut fields = vec![ParamField {
+        label: "other".to_string(),
+        value: String::new(),
+        hint: "NAME=value ...".to_string(),
+        kind: FieldKind::Overrides,
+    }];
+
+    let applicable = variables
+        .iter()
+        .filter(|v| v.target.is_none() || v.target.as_deref() == Some(task.name.as_str()));
+    for variable in applicable {
+        let hint = format!("{} {}", variable.op.as_str(), variable.value);
+        match fields
+            .iter_mut()
+            .find(|f| f.kind == FieldKind::Override && f.label == variable.name)
+        {
+            Some(field) => field.hint = hint,
+            None => fields.push(ParamField {
+                label: variable.name.clone(),
+                value: String::new(),
+                hint,
+                kind: FieldKind::Override,
+            }),
+        }
+    }
+    fields
+}
+
+fn parse_override(word: &str) -> Result<(String, String), String> {
+    match word.split_once('=') {
+        Some((name, value))
+            if !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.') =>
+        {
+            Ok((name.to_string(), value.to_string()))
+        }
+        _ => Err(format!("`{word}` is not a NAME=value assignment")),
+    }
+}
+
+impl Arguments {
+    // arguments typed after the task name: `NAME=value` overrides for a
+    // makefile target, values for the recipe parameters in order for a
+    // justfile recipe, the last variadic one taking the rest
+    pub fn from_args(task: &Task, args: &[String]) -> Result<Self, String> {
+        let mut arguments = Self::default();
+        if task.file_type == FileType::Makefile {
+            for arg in args {
+                arguments.overrides.push(parse_override(arg)?);
+            }
+            return Ok(arguments);
+        }
+
+        let mut rest = args;
+        for param in &task.parameters {
+            let count = if param.variadic.is_some() { rest.len() } else { rest.len().min(1) };
+            let (values, remaining) = rest.split_at(count);
+            rest = remaining;
+            if values.is_empty() {
+                if param.is_required() {
+                    return Err(format!("`{}` needs a value for `{}`", task.name, param.name));
+                }
+                continue;
+            }
+            arguments.positional.push((param.clone(), values.to_vec()));
+        }
+        if !rest.is_empty() {
+            return Err(format!(
+                "`{}` takes {} argument(s), `{}` is one too many",
+                task.name,
+                task.parameters.len(),
+                rest[0]
+            ));
+        }
+        Ok(arguments)
+    }
+
+    // the arguments of a justfile dependency call, `(build "release")`;
+    // only string literals can be evaluated without just
+    pub fn from_call(task: &Task, args: &[String]) -> Result<Self, String> {
+        let values = args
+            .iter()
+            .map(|arg| match unquote(arg) {
+                Some(value) => Ok(value.to_string()),
+                None => Err(format!(
+                    "`{}` is called with `{arg}`, which only just can evaluate; run it in delegate mode",
+                    task.name
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_args(task, &values)
+    }
+
+    // `{{param}}` interpolation and `$param` exports for running a recipe inline
+    pub fn apply(&self, task: &Task, commands: &[String]) -> (Vec<String>, Vec<(String, String)>) {
+        let mut env = self.overrides.clone();
+        let mut values: Vec<(String, String)> = Vec::new();
+
+        for param in &task.parameters {
+            let value = match self.positional.iter().find(|(p, _)| p.name == param.name) {
+                Some((_, values)) => values.join(" "),
+                None => match param.default.as_deref().and_then(unquote) {
+                    Some(default) => default.to_string(),
+                    None => continue,
+                },
+            };
+            if param.exported {
+                env.push((param.name.clone(), value.clone()));
+            }
+            values.push((param.name.clone(), value));
+        }
+
+        let commands = commands
+            .iter()
+            .map(|cmd| interpolate(cmd, &values))
+            .collect();
+        (commands, env)
+    }
+}
+
+fn interpolate(cmd: &str, values: &[(String, String)]) -> String {
+    let mut out = String::with_capacity(cmd.len());
+    let mut rest = cmd;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let inner = rest[start + 2..start + len].trim();
+        out.push_str(&rest[..start]);
+        match values.iter().find(|(name, _)| name == inner) {
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{AssignOp, Location};
+    use std::path::PathBuf;
+
+    fn make_target(name: &str) -> Task {
+        Task {
+            name: name.to_string(),
+            description: String::new(),
+            dependencies: Vec::new(),
+            subsequents: Vec::new(),
+            make_functions: Vec::new(),
+            commands: vec!["$(CC) -o app main.c".to_string()],
+            raw_commands: vec!["$(CC) -o app main.c".to_string()],
+            file_type: FileType::Makefile,
+            location: Location {
+                file: PathBuf::from("Makefile"),
+                line: 1,
+            },
+            module: None,
+            parameters: Vec::new(),
+        }
+    }
+
+    fn variable(name: &str, value: &str) -> Variable {
+        Variable {
+            name: name.to_string(),
+            op: AssignOp::Simple,
+            value: value.to_string(),
+            exported: false,
+            overridden: false,
+            target: None,
+        }
+    }
+
+    #[test]
+    fn picked_variables_become_overrides() {
+        let task = make_target("build");
+        let variables = [variable("CC", "gcc"), variable("CFLAGS", "-O2")];
+        let mut form = ParamForm::for_task(&task, &variables, Timeouts::default());
+        let cc = form.fields.iter_mut().find(|f| f.label == "CC").unwrap();
+        cc.value = "clang".to_string();
+        form.fields[0].value = "V=1 CC=tcc".to_string();
+
+        let arguments = form.validate(&task).unwrap();
+        assert_eq!(
+            arguments.overrides,
+            vec![
+                ("CC".to_string(), "clang".to_string()),
+                ("V".to_string(), "1".to_string()),
+                ("CC".to_string(), "tcc".to_string()),
+            ]
+        );
+        assert!(arguments.positional.is_empty());
+    }
+
+    #[test]
+    fn empty_picker_fields_are_left_out() {
+        let task = make_target("build");
+        let form = ParamForm::for_task(&task, &[variable("CC", "gcc")], Timeouts::default());
+        assert_eq!(form.validate(&task).unwrap(), Arguments::default());
+    }
+
+    #[test]
+    fn a_variable_named_other_gets_its_own_field() {
+        let task = make_target("build");
+        let form = ParamForm::for_task(&task, &[variable("other", "x")], Timeouts::default());
+        let labels: Vec<(&str, FieldKind)> = form.fields.iter().map(|f| (f.label.as_str(), f.kind)).collect();
+        assert!(labels.contains(&("other", FieldKind::Overrides)));
+        assert!(labels.contains(&("other", FieldKind::Override)));
+    }
+}
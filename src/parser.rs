@@ -1,14 +1,131 @@
+mod justfile;
+mod makefile;
+mod variables;
+
 use anyhow::{anyhow, Result};
-use std::path::Path;
-use std::fs;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct Task {
     pub name: String,
     pub description: String,
-    pub dependencies: Vec<String>,
+    pub dependencies: Vec<Dependency>,
+    // justfile dependencies after `&&`, run once the recipe has succeeded
+    pub subsequents: Vec<Dependency>,
     pub commands: Vec<String>,
+    pub raw_commands: Vec<String>,
+    // make functions the recipe calls, `shell`, `wildcard` and the like, and
+    // `*` for the stem; they are not evaluated, so only make can run such a
+    // recipe
+    pub make_functions: Vec<String>,
     pub file_type: FileType,
+    pub location: Location,
+    pub module: Option<String>,
+    pub parameters: Vec<Parameter>,
+}
+
+// a dependency as written after the colon; a justfile recipe can be called
+// with arguments, `(build "release")`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    pub name: String,
+    pub args: Vec<String>,
+    // a make prerequisite after `|`, built first but left out of `$^`
+    pub order_only: bool,
+}
+
+impl Dependency {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            args: Vec::new(),
+            order_only: false,
+        }
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.args.is_empty() {
+            return write!(f, "{}", self.name);
+        }
+        write!(f, "({} {})", self.name, self.args.join(" "))
+    }
+}
+
+// `build, (test "unit")` for the task list and details
+pub fn join_dependencies(dependencies: &[Dependency]) -> String {
+    dependencies
+        .iter()
+        .map(|dep| dep.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// an entry of `Task::make_functions` as it is written in the recipe
+pub fn make_reference(call: &str) -> String {
+    match call {
+        "*" => "$*".to_string(),
+        function => format!("$({function} ...)"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variadic {
+    OneOrMore,
+    ZeroOrMore,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub default: Option<String>,
+    pub variadic: Option<Variadic>,
+    pub exported: bool,
+}
+
+impl Parameter {
+    pub fn is_required(&self) -> bool {
+        self.default.is_none() && self.variadic != Some(Variadic::ZeroOrMore)
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.variadic {
+            Some(Variadic::OneOrMore) => write!(f, "+")?,
+            Some(Variadic::ZeroOrMore) => write!(f, "*")?,
+            None => {}
+        }
+        if self.exported {
+            write!(f, "$")?;
+        }
+        write!(f, "{}", self.name)?;
+        if let Some(default) = &self.default {
+            write!(f, "={default}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Task {
+    // recipes from a justfile module run in the directory of the module file
+    pub fn working_dir(&self) -> Option<&Path> {
+        self.module.as_ref().and(self.location.file.parent())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -17,184 +134,141 @@ pub enum FileType {
     Justfile,
 }
 
-pub struct Parser {
-    file_type: FileType,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AssignOp {
+    Recursive,
+    Simple,
+    PosixSimple,
+    Conditional,
+    Append,
+    Shell,
 }
 
-impl Parser {
-    pub fn detect_and_parse(dir: &Path) -> Result<(Vec<Task>, FileType)> {
-        if dir.join("justfile").exists() || dir.join("Justfile").exists() {
-            let path = if dir.join("justfile").exists() {
-                dir.join("justfile")
-            } else {
-                dir.join("Justfile")
-            };
-            let content = fs::read_to_string(&path)?;
-            Parser::parse_justfile(&content)
-        } else if dir.join("Makefile").exists() {
-            let content = fs::read_to_string(dir.join("Makefile"))?;
-            Parser::parse_makefile(&content)
-        } else {
-            Err(anyhow!("No Makefile or Justfile found in current directory"))
+impl AssignOp {
+    pub fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "=" => Some(AssignOp::Recursive),
+            ":=" => Some(AssignOp::Simple),
+            "::=" => Some(AssignOp::PosixSimple),
+            "?=" => Some(AssignOp::Conditional),
+            "+=" => Some(AssignOp::Append),
+            "!=" => Some(AssignOp::Shell),
+            _ => None,
         }
     }
 
-    fn parse_makefile(content: &str) -> Result<(Vec<Task>, FileType)> {
-        let mut tasks = Vec::new();
-        let mut current_target: Option<String> = None;
-        let mut current_deps: Vec<String> = Vec::new();
-        let mut current_commands: Vec<String> = Vec::new();
-        let mut current_description = String::new();
-
-        let lines: Vec<&str> = content.lines().collect();
-        let mut i = 0;
-
-        while i < lines.len() {
-            let line = lines[i];
-
-            if line.starts_with('#') && i + 1 < lines.len() {
-                let next_line = lines[i + 1];
-                if !next_line.starts_with('\t') && !next_line.starts_with(' ') && next_line.contains(':') {
-                    current_description = line.trim_start_matches('#').trim().to_string();
-                }
-            }
-
-            if !line.starts_with('\t') && !line.starts_with(' ') && line.contains(':') && !line.starts_with('#') {
-                if let Some(target) = current_target.take() {
-                    tasks.push(Task {
-                        name: target,
-                        description: current_description.clone(),
-                        dependencies: current_deps.clone(),
-                        commands: current_commands.clone(),
-                        file_type: FileType::Makefile,
-                    });
-                    current_deps.clear();
-                    current_commands.clear();
-                    current_description.clear();
-                }
-
-                let parts: Vec<&str> = line.split(':').collect();
-                current_target = Some(parts[0].trim().to_string());
-                if parts.len() > 1 {
-                    current_deps = parts[1]
-                        .split_whitespace()
-                        .map(|s| s.to_string())
-                        .collect();
-                }
-            } else if line.starts_with('\t') {
-                let cmd = line.trim_start_matches('\t').to_string();
-                if !cmd.is_empty() {
-                    current_commands.push(cmd);
-                }
-            }
-
-            i += 1;
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AssignOp::Recursive => "=",
+            AssignOp::Simple => ":=",
+            AssignOp::PosixSimple => "::=",
+            AssignOp::Conditional => "?=",
+            AssignOp::Append => "+=",
+            AssignOp::Shell => "!=",
         }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub op: AssignOp,
+    pub value: String,
+    pub exported: bool,
+    pub overridden: bool,
+    pub target: Option<String>,
+}
 
-        if let Some(target) = current_target {
-            tasks.push(Task {
-                name: target,
-                description: current_description,
-                dependencies: current_deps,
-                commands: current_commands,
-                file_type: FileType::Makefile,
-            });
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(target) = &self.target {
+            write!(f, "{target}: ")?;
+        }
+        if self.overridden {
+            write!(f, "override ")?;
+        }
+        if self.exported {
+            write!(f, "export ")?;
         }
+        write!(f, "{} {} {}", self.name, self.op.as_str(), self.value)
+    }
+}
 
-        Ok((tasks, FileType::Makefile))
-    }
-
-    fn parse_justfile(content: &str) -> Result<(Vec<Task>, FileType)> {
-        let mut tasks = Vec::new();
-        let lines: Vec<&str> = content.lines().collect();
-        let mut i = 0;
-
-        while i < lines.len() {
-            let line = lines[i].trim();
-
-            if line.starts_with('#') && i + 1 < lines.len() {
-                let next_line = lines[i + 1].trim();
-                if next_line.starts_with(|c: char| c.is_alphabetic() || c == '_') && next_line.contains(':') {
-                    let description = line.trim_start_matches('#').trim().to_string();
-                    let recipe_line = next_line;
-
-                    let parts: Vec<&str> = recipe_line.split(':').collect();
-                    let name = parts[0].trim().to_string();
-                    let deps_str = if parts.len() > 1 { parts[1] } else { "" };
-
-                    let dependencies: Vec<String> = deps_str
-                        .split_whitespace()
-                        .filter(|s| !s.is_empty())
-                        .map(|s| s.to_string())
-                        .collect();
-
-                    let mut commands = Vec::new();
-                    i += 2;
-
-                    while i < lines.len() {
-                        let cmd_line = lines[i];
-                        if cmd_line.starts_with(' ') || cmd_line.starts_with('\t') {
-                            let cmd = cmd_line.trim().to_string();
-                            if !cmd.is_empty() {
-                                commands.push(cmd);
-                            }
-                            i += 1;
-                        } else {
-                            break;
-                        }
-                    }
-
-                    tasks.push(Task {
-                        name,
-                        description,
-                        dependencies,
-                        commands,
-                        file_type: FileType::Justfile,
-                    });
-                    continue;
-                }
-            }
-
-            if line.starts_with(|c: char| c.is_alphabetic() || c == '_') && line.contains(':') && !line.starts_with('#') {
-                let parts: Vec<&str> = line.split(':').collect();
-                let name = parts[0].trim().to_string();
-                let deps_str = if parts.len() > 1 { parts[1] } else { "" };
-
-                let dependencies: Vec<String> = deps_str
-                    .split_whitespace()
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.to_string())
-                    .collect();
-
-                let mut commands = Vec::new();
-                i += 1;
-
-                while i < lines.len() {
-                    let cmd_line = lines[i];
-                    if cmd_line.starts_with(' ') || cmd_line.starts_with('\t') {
-                        let cmd = cmd_line.trim().to_string();
-                        if !cmd.is_empty() {
-                            commands.push(cmd);
-                        }
-                        i += 1;
-                    } else {
-                        break;
-                    }
-                }
-
-                tasks.push(Task {
-                    name,
-                    description: String::new(),
-                    dependencies,
-                    commands,
-                    file_type: FileType::Justfile,
-                });
-                continue;
-            }
-
-            i += 1;
+#[derive(Debug, Clone)]
+pub struct ParsedFile {
+    pub tasks: Vec<Task>,
+    pub variables: Vec<Variable>,
+    pub file_type: FileType,
+    // problems that did not stop the parse, shown with the config errors
+    pub errors: Vec<String>,
+}
+
+pub struct Parser;
+
+impl Parser {
+    // a file named like a justfile is read as one, anything else as a makefile,
+    // unless the type is given; `dir` is where make runs
+    pub fn parse_file(path: &Path, dir: &Path, file_type: Option<FileType>) -> Result<ParsedFile> {
+        let is_justfile = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.to_ascii_lowercase().contains("justfile") || name.ends_with(".just"));
+        match file_type {
+            Some(FileType::Justfile) => Parser::parse_justfile(path),
+            Some(FileType::Makefile) => Parser::parse_makefile(path, dir),
+            None if is_justfile => Parser::parse_justfile(path),
+            None => Parser::parse_makefile(path, dir),
         }
+    }
 
-        Ok((tasks, FileType::Justfile))
+    // a justfile wins over a makefile in the same directory unless a type
+    // is given
+    pub fn detect_and_parse(dir: &Path, file_type: Option<FileType>) -> Result<ParsedFile> {
+        let justfile = ["justfile", "Justfile"]
+            .map(|name| dir.join(name))
+            .into_iter()
+            .find(|path| path.exists());
+        let makefile = Some(dir.join("Makefile")).filter(|path| path.exists());
+        match (file_type, justfile, makefile) {
+            (None | Some(FileType::Justfile), Some(path), _) => Parser::parse_justfile(&path),
+            (None | Some(FileType::Makefile), _, Some(path)) => Parser::parse_makefile(&path, dir),
+            (None, None, None) => Err(anyhow!("No Makefile or Justfile found in {}", dir.display())),
+            (Some(FileType::Justfile), None, _) => Err(anyhow!("No Justfile found in {}", dir.display())),
+            (Some(FileType::Makefile), _, None) => Err(anyhow!("No Makefile found in {}", dir.display())),
+        }
+    }
+
+    // re-expands a makefile recipe as if `NAME=value` was given to make
+    pub fn expand_recipe(
+        task: &Task,
+        variables: &[Variable],
+        overrides: &[(String, String)],
+    ) -> Vec<String> {
+        makefile::expand_recipe(task, variables, overrides)
     }
+
+    fn parse_makefile(path: &Path, dir: &Path) -> Result<ParsedFile> {
+        makefile::parse(path, dir)
+    }
+
+    fn parse_justfile(path: &Path) -> Result<ParsedFile> {
+        justfile::parse(path)
+    }
+}
+
+// the first whitespace-separated word and the rest of the line
+pub(crate) fn split_first_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    match text.find(char::is_whitespace) {
+        Some(pos) => (&text[..pos], text[pos..].trim_start()),
+        None => (text, ""),
+    }
+}
+
+// the text inside matching single or double quotes
+pub(crate) fn unquote(text: &str) -> Option<&str> {
+    let text = text.trim();
+    ['\'', '"']
+        .into_iter()
+        .find_map(|q| text.strip_prefix(q).and_then(|t| t.strip_suffix(q)))
 }
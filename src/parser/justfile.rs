@@ -0,0 +1,530 @@
+use super::{split_first_word, unquote, Dependency, FileType, Location, Parameter, ParsedFile, Task, Variadic};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn parse(path: &Path) -> Result<ParsedFile> {
+    let mut reader = Reader::default();
+    reader.read_file(path, None, None)?;
+    Ok(ParsedFile {
+        tasks: reader.tasks,
+        variables: Vec::new(),
+        file_type: FileType::Justfile,
+        errors: Vec::new(),
+    })
+}
+
+#[derive(Default)]
+struct Reader {
+    tasks: Vec<Task>,
+    import_stack: Vec<PathBuf>,
+}
+
+impl Reader {
+    fn read_file(
+        &mut self,
+        path: &Path,
+        module: Option<&str>,
+        imported_from: Option<&Location>,
+    ) -> Result<()> {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if self.import_stack.contains(&canonical) {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(path).map_err(|e| match imported_from {
+            Some(location) => anyhow!("{location}: cannot import {}: {e}", path.display()),
+            None => anyhow!("cannot read {}: {e}", path.display()),
+        })?;
+
+        self.import_stack.push(canonical);
+        let result = self.read_lines(path, module, &content);
+        self.import_stack.pop();
+        result
+    }
+
+    fn read_lines(&mut self, path: &Path, module: Option<&str>, content: &str) -> Result<()> {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let lines: Vec<&str> = content.lines().collect();
+        let mut description = String::new();
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i].trim();
+            let location = Location {
+                file: path.to_path_buf(),
+                line: i + 1,
+            };
+            i += 1;
+
+            if let Some(comment) = line.strip_prefix('#') {
+                description = comment.trim().to_string();
+                continue;
+            }
+            // attributes, `[private]` or `[group('ci')]`, sit between a
+            // recipe's comment and its header
+            if line.starts_with('[') && line.ends_with(']') {
+                continue;
+            }
+
+            let (word, rest) = split_first_word(line);
+            match word {
+                "import" | "import?" => {
+                    let Some(file) = unquote(rest) else {
+                        description.clear();
+                        continue;
+                    };
+                    let target = dir.join(file);
+                    if word == "import?" && !target.exists() {
+                        continue;
+                    }
+                    self.read_file(&target, module, Some(&location))?;
+                }
+                "mod" | "mod?" => {
+                    let (name, explicit) = split_first_word(rest);
+                    let source = match unquote(explicit) {
+                        Some(file) => Some(dir.join(file)),
+                        None => find_module_source(dir, name),
+                    };
+                    let qualified = qualify(module, name);
+                    match source {
+                        Some(source) => {
+                            self.read_file(&source, Some(&qualified), Some(&location))?
+                        }
+                        None if word == "mod?" => {}
+                        None => {
+                            return Err(anyhow!(
+                                "{location}: source file for module `{name}` not found"
+                            ))
+                        }
+                    }
+                }
+                _ => {
+                    let Some(header) = parse_recipe_header(line) else {
+                        description.clear();
+                        continue;
+                    };
+                    let qualified = |deps: Vec<Dependency>| -> Vec<Dependency> {
+                        deps.into_iter()
+                            .map(|dep| Dependency {
+                                name: qualify(module, &dep.name),
+                                ..dep
+                            })
+                            .collect()
+                    };
+
+                    let mut commands = Vec::new();
+                    while i < lines.len() {
+                        let cmd_line = lines[i];
+                        if cmd_line.starts_with(' ') || cmd_line.starts_with('\t') {
+                            let cmd = cmd_line.trim().to_string();
+                            if !cmd.is_empty() {
+                                commands.push(cmd);
+                            }
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+
+                    self.tasks.push(Task {
+                        name: qualify(module, &header.name),
+                        description: std::mem::take(&mut description),
+                        dependencies: qualified(header.dependencies),
+                        subsequents: qualified(header.subsequents),
+                        make_functions: Vec::new(),
+                        raw_commands: commands.clone(),
+                        commands,
+                        file_type: FileType::Justfile,
+                        location,
+                        module: module.map(|m| m.to_string()),
+                        parameters: header.parameters,
+                    });
+                    continue;
+                }
+            }
+
+            description.clear();
+        }
+
+        Ok(())
+    }
+}
+
+struct RecipeHeader {
+    name: String,
+    parameters: Vec<Parameter>,
+    dependencies: Vec<Dependency>,
+    subsequents: Vec<Dependency>,
+}
+
+// `[@]name [+|*][$]param[=default]... : dep (dep arg)... [&& dep...]`
+fn parse_recipe_header(line: &str) -> Option<RecipeHeader> {
+    let line = line.strip_prefix('@').unwrap_or(line);
+    let mut scanner = Scanner::new(line);
+    let name = scanner.identifier()?;
+
+    let mut parameters = Vec::new();
+    loop {
+        scanner.skip_whitespace();
+        match scanner.peek()? {
+            ':' => break,
+            '+' | '*' | '$' => {}
+            c if is_identifier_start(c) => {}
+            _ => return None,
+        }
+
+        let variadic = match scanner.peek() {
+            Some('+') => Some(Variadic::OneOrMore),
+            Some('*') => Some(Variadic::ZeroOrMore),
+            _ => None,
+        };
+        if variadic.is_some() {
+            scanner.bump();
+        }
+        let exported = scanner.eat('$');
+        let param_name = scanner.identifier()?;
+
+        scanner.skip_whitespace();
+        let default = if scanner.eat('=') {
+            scanner.skip_whitespace();
+            Some(scanner.value()?)
+        } else {
+            None
+        };
+
+        parameters.push(Parameter {
+            name: param_name,
+            default,
+            variadic,
+            exported,
+        });
+    }
+
+    scanner.bump();
+    // `name := value` is an assignment, not a recipe
+    if scanner.peek() == Some('=') {
+        return None;
+    }
+
+    let mut dependencies = Vec::new();
+    let mut subsequents = Vec::new();
+    // dependencies after `&&` run once the recipe has succeeded
+    let mut after = false;
+    loop {
+        scanner.skip_whitespace();
+        match scanner.peek() {
+            None | Some('#') => break,
+            Some('&') => {
+                scanner.bump();
+                if !scanner.eat('&') {
+                    return None;
+                }
+                after = true;
+            }
+            Some(_) if after => subsequents.push(parse_dependency(&mut scanner)?),
+            Some(_) => dependencies.push(parse_dependency(&mut scanner)?),
+        }
+    }
+
+    Some(RecipeHeader {
+        name,
+        parameters,
+        dependencies,
+        subsequents,
+    })
+}
+
+// `name` or `(name arg...)`, the arguments kept as written
+fn parse_dependency(scanner: &mut Scanner) -> Option<Dependency> {
+    if scanner.peek() != Some('(') {
+        return Some(Dependency::new(scanner.identifier()?));
+    }
+    let call = scanner.value()?;
+    let mut inner = Scanner::new(call.strip_prefix('(')?.strip_suffix(')')?);
+    inner.skip_whitespace();
+    let name = inner.identifier()?;
+    let mut args = Vec::new();
+    loop {
+        inner.skip_whitespace();
+        if inner.peek().is_none() {
+            break;
+        }
+        args.push(inner.value()?);
+    }
+    Some(Dependency {
+        name,
+        args,
+        order_only: false,
+    })
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+struct Scanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        if !self.peek().is_some_and(is_identifier_start) {
+            return None;
+        }
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        {
+            self.bump();
+        }
+        Some(self.text[start..self.pos].to_string())
+    }
+
+    // a default value or dependency call: a quoted string, a backtick
+    // command, a parenthesized expression or a bare word
+    fn value(&mut self) -> Option<String> {
+        let start = self.pos;
+        match self.peek()? {
+            quote @ ('\'' | '"' | '`') => {
+                self.bump();
+                loop {
+                    match self.bump()? {
+                        '\\' if quote == '"' => {
+                            self.bump();
+                        }
+                        c if c == quote => break,
+                        _ => {}
+                    }
+                }
+            }
+            '(' => {
+                let mut depth = 0usize;
+                loop {
+                    match self.bump()? {
+                        '(' => depth += 1,
+                        ')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        quote @ ('\'' | '"' | '`') => {
+                            while self.bump()? != quote {}
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !c.is_whitespace() && c != ':')
+                {
+                    self.bump();
+                }
+            }
+        }
+        (self.pos > start).then(|| self.text[start..self.pos].to_string())
+    }
+}
+
+fn qualify(module: Option<&str>, name: &str) -> String {
+    match module {
+        Some(module) => format!("{module}::{name}"),
+        None => name.to_string(),
+    }
+}
+
+// `mod name` looks for `name.just`, then `name/mod.just`, then `name/justfile`
+fn find_module_source(dir: &Path, name: &str) -> Option<PathBuf> {
+    let candidates = [
+        dir.join(format!("{name}.just")),
+        dir.join(name).join("mod.just"),
+        dir.join(name).join("justfile"),
+        dir.join(name).join("Justfile"),
+        dir.join(name).join(".justfile"),
+    ];
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(deps: &[Dependency]) -> Vec<&str> {
+        deps.iter().map(|dep| dep.name.as_str()).collect()
+    }
+
+    // writes `files` into a directory of the test's own and parses the
+    // first one
+    fn parse_files(test: &str, files: &[(&str, &str)]) -> Result<ParsedFile> {
+        let dir = std::env::temp_dir().join(format!("lazymake-justfile-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (name, content) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let parsed = parse(&dir.join(files[0].0));
+        fs::remove_dir_all(&dir).unwrap();
+        parsed
+    }
+
+    fn task_names(parsed: &ParsedFile) -> Vec<&str> {
+        parsed.tasks.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    #[test]
+    fn dependencies_after_and_run_later() {
+        let header = parse_recipe_header("test: build && cleanup report").unwrap();
+        assert_eq!(header.name, "test");
+        assert_eq!(names(&header.dependencies), ["build"]);
+        assert_eq!(names(&header.subsequents), ["cleanup", "report"]);
+    }
+
+    #[test]
+    fn dependency_calls_keep_their_arguments() {
+        let header = parse_recipe_header(r#"a: (b "x") (c 'y' z) d && (e "done")"#).unwrap();
+        assert_eq!(
+            header.dependencies,
+            [
+                Dependency {
+                    name: "b".to_string(),
+                    args: vec!["\"x\"".to_string()],
+                    order_only: false,
+                },
+                Dependency {
+                    name: "c".to_string(),
+                    args: vec!["'y'".to_string(), "z".to_string()],
+                    order_only: false,
+                },
+                Dependency::new("d"),
+            ]
+        );
+        assert_eq!(header.subsequents[0].args, ["\"done\""]);
+    }
+
+    #[test]
+    fn assignments_are_not_recipes() {
+        assert!(parse_recipe_header("version := \"1.0\"").is_none());
+    }
+
+    #[test]
+    fn attributes_keep_the_comment() {
+        let mut reader = Reader::default();
+        let content = "# deploy to staging\n[private]\n[group('ci')]\ndeploy env=\"staging\":\n    ./deploy {{env}}\n";
+        reader.read_lines(Path::new("justfile"), None, content).unwrap();
+        let [task] = reader.tasks.as_slice() else {
+            panic!("expected one recipe, got {}", reader.tasks.len());
+        };
+        assert_eq!(task.name, "deploy");
+        assert_eq!(task.description, "deploy to staging");
+        assert_eq!(task.parameters[0].default.as_deref(), Some("\"staging\""));
+        assert_eq!(task.commands, ["./deploy {{env}}"]);
+    }
+
+    #[test]
+    fn imports_add_recipes_to_the_same_namespace() {
+        let parsed = parse_files(
+            "import",
+            &[
+                ("justfile", "import 'ci/tasks.just'\nimport? 'local.just'\nbuild:\n  echo build\n"),
+                ("ci/tasks.just", "# run the checks\ncheck: build\n  echo check\n"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(task_names(&parsed), ["check", "build"]);
+        assert_eq!(parsed.tasks[0].description, "run the checks");
+        assert_eq!(parsed.tasks[0].module, None);
+    }
+
+    #[test]
+    fn missing_imports() {
+        let parsed = parse_files("optional-import", &[("justfile", "import? 'gone.just'\nbuild:\n  echo build\n")]);
+        assert_eq!(task_names(&parsed.unwrap()), ["build"]);
+
+        let error = parse_files("required-import", &[("justfile", "import 'gone.just'\n")]).unwrap_err();
+        assert!(error.to_string().contains("cannot import"), "{error}");
+    }
+
+    #[test]
+    fn module_sources_are_looked_up_in_order() {
+        let parsed = parse_files(
+            "mod-order",
+            &[
+                ("justfile", "mod a\nmod b\nmod c\nmod? d\nmod e 'elsewhere/e.just'\n"),
+                ("a.just", "from-a-just:\n  echo\n"),
+                ("a/mod.just", "from-a-mod:\n  echo\n"),
+                ("b/mod.just", "from-b-mod:\n  echo\n"),
+                ("b/justfile", "from-b-justfile:\n  echo\n"),
+                ("c/Justfile", "from-c-justfile:\n  echo\n"),
+                ("elsewhere/e.just", "from-e:\n  echo\n"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            task_names(&parsed),
+            ["a::from-a-just", "b::from-b-mod", "c::from-c-justfile", "e::from-e"]
+        );
+        assert_eq!(parsed.tasks[0].module.as_deref(), Some("a"));
+
+        let error = parse_files("mod-missing", &[("justfile", "mod gone\n")]).unwrap_err();
+        assert!(error.to_string().contains("module `gone` not found"), "{error}");
+    }
+
+    #[test]
+    fn dependencies_in_modules_are_qualified() {
+        let parsed = parse_files(
+            "mod-deps",
+            &[
+                ("justfile", "mod tools\n"),
+                ("tools.just", "mod lint\nbuild: fetch && (report 'done')\n  echo build\nfetch:\n  echo fetch\n"),
+                ("lint.just", "check: fix\n  echo check\n"),
+            ],
+        )
+        .unwrap();
+        let task = |name: &str| parsed.tasks.iter().find(|t| t.name == name).unwrap();
+        assert_eq!(names(&task("tools::build").dependencies), ["tools::fetch"]);
+        assert_eq!(
+            task("tools::build").subsequents,
+            [Dependency {
+                name: "tools::report".to_string(),
+                args: vec!["'done'".to_string()],
+                order_only: false,
+            }]
+        );
+        assert_eq!(names(&task("tools::lint::check").dependencies), ["tools::lint::fix"]);
+    }
+}
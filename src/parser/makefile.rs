@@ -0,0 +1,679 @@
+use super::variables::{AutoVars, VariableTable};
+use super::{split_first_word, AssignOp, Dependency, FileType, Location, ParsedFile, Task, Variable};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+enum Line {
+    Blank,
+    Comment(String),
+    Assignment(Variable),
+    TargetAssignment {
+        targets: String,
+        variable: Variable,
+    },
+    Define(Variable),
+    Include {
+        paths: String,
+        optional: bool,
+    },
+    Rule {
+        targets: String,
+        prerequisites: String,
+        inline_recipe: Option<String>,
+    },
+    Directive,
+}
+
+enum Statement<'a> {
+    Assignment(Variable),
+    Rule(&'a str, &'a str),
+}
+
+// `working_dir` is where make runs, which is what includes are relative to
+pub fn parse(path: &Path, working_dir: &Path) -> Result<ParsedFile> {
+    let mut reader = Reader {
+        working_dir: working_dir.to_path_buf(),
+        ..Reader::default()
+    };
+    reader.read_file(path, None)?;
+    Ok(reader.finish())
+}
+
+#[derive(Default)]
+struct Reader {
+    working_dir: PathBuf,
+    tasks: Vec<Task>,
+    variables: Vec<Variable>,
+    table: VariableTable,
+    include_stack: Vec<PathBuf>,
+    // includes that could not be read; the rest of the file still counts
+    errors: Vec<String>,
+}
+
+impl Reader {
+    fn read_file(&mut self, path: &Path, included_from: Option<&Location>) -> Result<()> {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if self.include_stack.contains(&canonical) {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(path).map_err(|e| match included_from {
+            Some(location) => anyhow!("{location}: cannot include {}: {e}", path.display()),
+            None => anyhow!("cannot read {}: {e}", path.display()),
+        })?;
+
+        self.include_stack.push(canonical);
+        self.read_lines(path, &content);
+        self.include_stack.pop();
+        Ok(())
+    }
+
+    fn read_lines(&mut self, path: &Path, content: &str) {
+        let lines: Vec<&str> = content.lines().collect();
+        // targets of the rule being read, and whether its recipe has started
+        let mut current: Vec<(usize, bool)> = Vec::new();
+        let mut description = String::new();
+        let mut i = 0;
+
+        while i < lines.len() {
+            if lines[i].starts_with('\t') && !current.is_empty() {
+                let cmd = join_recipe_line(&lines, &mut i);
+                if !cmd.trim().is_empty() {
+                    for (idx, started) in &mut current {
+                        // a later recipe for the same target replaces the earlier one
+                        let task = &mut self.tasks[*idx];
+                        if !*started {
+                            task.raw_commands.clear();
+                            *started = true;
+                        }
+                        task.raw_commands.push(cmd.clone());
+                    }
+                }
+                continue;
+            }
+
+            let location = Location {
+                file: path.to_path_buf(),
+                line: i + 1,
+            };
+            let text = join_logical_line(&lines, &mut i);
+            match classify(&text) {
+                Line::Blank => description.clear(),
+                Line::Comment(comment) => description = comment,
+                Line::Assignment(variable) => {
+                    current.clear();
+                    description.clear();
+                    self.table.assign(&variable);
+                    self.variables.push(variable);
+                }
+                Line::TargetAssignment { targets, variable } => {
+                    current.clear();
+                    description.clear();
+                    for target in self.table.expand(&targets, None).split_whitespace() {
+                        self.variables.push(Variable {
+                            target: Some(target.to_string()),
+                            ..variable.clone()
+                        });
+                    }
+                }
+                Line::Define(mut variable) => {
+                    current.clear();
+                    description.clear();
+                    let mut body = Vec::new();
+                    while i < lines.len() && lines[i].trim() != "endef" {
+                        body.push(lines[i]);
+                        i += 1;
+                    }
+                    i += 1;
+                    variable.value = body.join("\n");
+                    self.table.assign(&variable);
+                    self.variables.push(variable);
+                }
+                Line::Include { paths, optional } => {
+                    current.clear();
+                    description.clear();
+                    self.include(&paths, optional, &location);
+                }
+                Line::Rule {
+                    targets,
+                    prerequisites,
+                    inline_recipe,
+                } => {
+                    current.clear();
+                    self.add_rule(
+                        &targets,
+                        &prerequisites,
+                        inline_recipe,
+                        &description,
+                        &location,
+                        &mut current,
+                    );
+                    description.clear();
+                }
+                Line::Directive => description.clear(),
+            }
+        }
+    }
+
+    fn include(&mut self, paths: &str, optional: bool, location: &Location) {
+        for pattern in self.table.expand(paths, None).split_whitespace() {
+            let full = self.working_dir.join(pattern);
+            let matches: Vec<PathBuf> = if pattern.contains(['*', '?', '[']) {
+                glob::glob(&full.to_string_lossy())
+                    .map(|paths| paths.filter_map(|p| p.ok()).collect())
+                    .unwrap_or_default()
+            } else {
+                vec![full]
+            };
+
+            for path in matches {
+                if optional && !path.exists() {
+                    continue;
+                }
+                if let Err(e) = self.read_file(&path, Some(location)) {
+                    self.errors.push(e.to_string());
+                }
+            }
+        }
+    }
+
+    fn add_rule(
+        &mut self,
+        targets: &str,
+        prerequisites: &str,
+        inline_recipe: Option<String>,
+        description: &str,
+        location: &Location,
+        current: &mut Vec<(usize, bool)>,
+    ) {
+        let prerequisites = self.table.expand(prerequisites, None);
+        let (normal, order_only) = prerequisites.split_once('|').unwrap_or((&prerequisites, ""));
+        let prerequisites: Vec<Dependency> = normal
+            .split_whitespace()
+            .map(Dependency::new)
+            .chain(order_only.split_whitespace().map(|name| Dependency {
+                order_only: true,
+                ..Dependency::new(name)
+            }))
+            .collect();
+
+        for target in self
+            .table
+            .expand(targets, None)
+            .split_whitespace()
+            .filter(|t| is_task_target(t))
+        {
+            let idx = match self.tasks.iter().position(|t| t.name == target) {
+                Some(idx) => idx,
+                None => {
+                    self.tasks.push(Task {
+                        name: target.to_string(),
+                        description: String::new(),
+                        dependencies: Vec::new(),
+                        subsequents: Vec::new(),
+                        make_functions: Vec::new(),
+                        commands: Vec::new(),
+                        raw_commands: Vec::new(),
+                        file_type: FileType::Makefile,
+                        location: location.clone(),
+                        module: None,
+                        parameters: Vec::new(),
+                    });
+                    self.tasks.len() - 1
+                }
+            };
+
+            let task = &mut self.tasks[idx];
+            if task.description.is_empty() {
+                task.description = description.to_string();
+            }
+            // a prerequisite that is also a normal one is not order-only
+            for dep in &prerequisites {
+                match task.dependencies.iter_mut().find(|d| d.name == dep.name) {
+                    Some(existing) => existing.order_only &= dep.order_only,
+                    None => task.dependencies.push(dep.clone()),
+                }
+            }
+            if let Some(cmd) = &inline_recipe {
+                task.raw_commands = vec![cmd.clone()];
+            }
+            current.push((idx, inline_recipe.is_some()));
+        }
+    }
+
+    // recipes are expanded after the whole file is read, like make does
+    fn finish(mut self) -> ParsedFile {
+        for task in &mut self.tasks {
+            let mut calls = Vec::new();
+            task.commands = expand_with(&self.table, task, &self.variables, &mut calls);
+            task.make_functions = calls;
+        }
+
+        ParsedFile {
+            tasks: self.tasks,
+            variables: self.variables,
+            file_type: FileType::Makefile,
+            errors: self.errors,
+        }
+    }
+}
+
+pub fn expand_recipe(
+    task: &Task,
+    variables: &[Variable],
+    overrides: &[(String, String)],
+) -> Vec<String> {
+    let mut table = VariableTable::with_overrides(overrides);
+    for variable in variables.iter().filter(|v| v.target.is_none()) {
+        table.assign(variable);
+    }
+    expand_with(&table, task, variables, &mut Vec::new())
+}
+
+// target-specific variables are layered over the global ones
+fn expand_with(
+    table: &VariableTable,
+    task: &Task,
+    variables: &[Variable],
+    calls: &mut Vec<String>,
+) -> Vec<String> {
+    let mut scoped = table.clone();
+    for variable in variables
+        .iter()
+        .filter(|v| v.target.as_deref() == Some(task.name.as_str()))
+    {
+        scoped.assign(variable);
+    }
+
+    let (order_only, normal): (Vec<&Dependency>, Vec<&Dependency>) =
+        task.dependencies.iter().partition(|d| d.order_only);
+    let names = |deps: Vec<&Dependency>| -> Vec<String> { deps.into_iter().map(|d| d.name.clone()).collect() };
+    let prerequisites = names(normal);
+    let order_only = names(order_only);
+    let auto = AutoVars {
+        target: &task.name,
+        prerequisites: &prerequisites,
+        order_only: &order_only,
+    };
+    task.raw_commands
+        .iter()
+        .map(|cmd| scoped.expand_noting_calls(cmd, Some(&auto), calls))
+        .collect()
+}
+
+fn classify(text: &str) -> Line {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Line::Blank;
+    }
+    if let Some(comment) = trimmed.strip_prefix('#') {
+        return Line::Comment(comment.trim().to_string());
+    }
+
+    let rest = strip_comment(trimmed).trim();
+    if rest.is_empty() {
+        return Line::Blank;
+    }
+
+    let (rest, exported, overridden) = strip_modifiers(rest);
+    let (word, tail) = split_first_word(rest);
+    match word {
+        "define" => {
+            let (name, op) = match tail.rsplit_once(char::is_whitespace) {
+                Some((name, op)) => match AssignOp::from_token(op.trim()) {
+                    Some(op) => (name.trim(), op),
+                    None => (tail.trim(), AssignOp::Recursive),
+                },
+                None => (tail.trim(), AssignOp::Recursive),
+            };
+            return Line::Define(Variable {
+                name: name.to_string(),
+                op,
+                value: String::new(),
+                exported,
+                overridden,
+                target: None,
+            });
+        }
+        "include" | "-include" | "sinclude" => {
+            return Line::Include {
+                paths: tail.to_string(),
+                optional: word != "include",
+            };
+        }
+        "" | "ifeq" | "ifneq" | "ifdef" | "ifndef" | "else" | "endif" | "unexport" | "vpath" => {
+            return Line::Directive
+        }
+        _ => {}
+    }
+
+    match statement(rest, exported, overridden) {
+        Some(Statement::Assignment(variable)) => Line::Assignment(variable),
+        Some(Statement::Rule(targets, after)) if !exported && !overridden => rule(targets, after),
+        _ => Line::Directive,
+    }
+}
+
+fn strip_modifiers(mut text: &str) -> (&str, bool, bool) {
+    let mut exported = false;
+    let mut overridden = false;
+    loop {
+        let (word, tail) = split_first_word(text);
+        match word {
+            "export" => exported = true,
+            "override" => overridden = true,
+            _ => return (text, exported, overridden),
+        }
+        text = tail;
+    }
+}
+
+fn statement(text: &str, exported: bool, overridden: bool) -> Option<Statement<'_>> {
+    let assignment = |start: usize, op: AssignOp, op_len: usize| {
+        Statement::Assignment(Variable {
+            name: text[..start].trim().to_string(),
+            op,
+            value: text[start + op_len..].trim().to_string(),
+            exported,
+            overridden,
+            target: None,
+        })
+    };
+
+    match find_separator(text)? {
+        (pos, ':') => {
+            let after = &text[pos + 1..];
+            if after.starts_with('=') {
+                Some(assignment(pos, AssignOp::Simple, 2))
+            } else if after.starts_with(":=") {
+                Some(assignment(pos, AssignOp::PosixSimple, 3))
+            } else {
+                Some(Statement::Rule(
+                    &text[..pos],
+                    after.strip_prefix(':').unwrap_or(after),
+                ))
+            }
+        }
+        (pos, _) => {
+            let (op, start) = match text[..pos].chars().last() {
+                Some('?') => (AssignOp::Conditional, pos - 1),
+                Some('+') => (AssignOp::Append, pos - 1),
+                Some('!') => (AssignOp::Shell, pos - 1),
+                _ => (AssignOp::Recursive, pos),
+            };
+            Some(assignment(start, op, pos + 1 - start))
+        }
+    }
+}
+
+fn rule(targets: &str, rest: &str) -> Line {
+    let (prereqs, inline_recipe) = match find_char(rest, ';') {
+        Some(pos) => (&rest[..pos], Some(rest[pos + 1..].trim().to_string())),
+        None => (rest, None),
+    };
+
+    // `target: VAR = value` sets a target-specific variable
+    let (assign, exported, overridden) = strip_modifiers(prereqs);
+    if let Some(Statement::Assignment(variable)) = statement(assign, exported, overridden) {
+        return Line::TargetAssignment {
+            targets: targets.to_string(),
+            variable,
+        };
+    }
+
+    // static pattern rules: `targets: target-pattern: prereq-patterns`
+    let prereqs = match find_char(prereqs, ':') {
+        Some(pos) => &prereqs[pos + 1..],
+        None => prereqs,
+    };
+
+    Line::Rule {
+        targets: targets.to_string(),
+        prerequisites: prereqs.to_string(),
+        inline_recipe: inline_recipe.filter(|cmd| !cmd.is_empty()),
+    }
+}
+
+// finds the first `:` or `=` outside of `$(...)` / `${...}` references
+fn find_separator(text: &str) -> Option<(usize, char)> {
+    let mut depth = 0usize;
+    let mut prev = '\0';
+    for (pos, c) in text.char_indices() {
+        match c {
+            '(' | '{' if prev == '$' || depth > 0 => depth += 1,
+            ')' | '}' if depth > 0 => depth -= 1,
+            ':' | '=' if depth == 0 => return Some((pos, c)),
+            _ => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+fn find_char(text: &str, needle: char) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut prev = '\0';
+    for (pos, c) in text.char_indices() {
+        match c {
+            '(' | '{' if prev == '$' || depth > 0 => depth += 1,
+            ')' | '}' if depth > 0 => depth -= 1,
+            c if c == needle && depth == 0 => return Some(pos),
+            _ => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+fn strip_comment(text: &str) -> &str {
+    let mut prev = '\0';
+    for (pos, c) in text.char_indices() {
+        if c == '#' && prev != '\\' {
+            return &text[..pos];
+        }
+        prev = c;
+    }
+    text
+}
+
+// special targets such as `.PHONY` and pattern rules are never tasks
+fn is_task_target(target: &str) -> bool {
+    !target.contains('%') && (!target.starts_with('.') || target.contains('/'))
+}
+
+fn ends_with_continuation(line: &str) -> bool {
+    line.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1
+}
+
+fn join_logical_line(lines: &[&str], i: &mut usize) -> String {
+    let mut text = lines[*i].to_string();
+    *i += 1;
+    while ends_with_continuation(&text) && *i < lines.len() {
+        text.pop();
+        let trimmed_len = text.trim_end().len();
+        text.truncate(trimmed_len);
+        text.push(' ');
+        text.push_str(lines[*i].trim_start());
+        *i += 1;
+    }
+    text
+}
+
+fn join_recipe_line(lines: &[&str], i: &mut usize) -> String {
+    let mut cmd = lines[*i].trim_start_matches('\t').to_string();
+    *i += 1;
+    while ends_with_continuation(&cmd) && *i < lines.len() {
+        cmd.push('\n');
+        cmd.push_str(lines[*i].strip_prefix('\t').unwrap_or(lines[*i]));
+        *i += 1;
+    }
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(content: &str) -> ParsedFile {
+        let mut reader = Reader::default();
+        reader.read_lines(Path::new("Makefile"), content);
+        reader.finish()
+    }
+
+    // writes `files` into a directory of the test's own and parses the
+    // first one as the makefile, with make running in that directory
+    fn parse_files(test: &str, files: &[(&str, &str)]) -> ParsedFile {
+        let dir = std::env::temp_dir().join(format!("lazymake-makefile-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (name, content) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let parsed = parse(&dir.join(files[0].0), &dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        parsed
+    }
+
+    fn names(parsed: &ParsedFile) -> Vec<&str> {
+        parsed.tasks.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    fn commands<'a>(parsed: &'a ParsedFile, target: &str) -> &'a [String] {
+        let task = parsed.tasks.iter().find(|t| t.name == target).unwrap();
+        &task.commands
+    }
+
+    #[test]
+    fn assignment_operators() {
+        let parsed = parse_str(
+            "CC = gcc\n\
+             CFLAGS := -O2\n\
+             CC ?= clang\n\
+             CFLAGS += -Wall\n\
+             REV != git rev-parse HEAD\n\
+             build:\n\
+             \t$(CC) $(CFLAGS) -o app\n\
+             \techo $(REV)\n",
+        );
+        let ops: Vec<AssignOp> = parsed.variables.iter().map(|v| v.op).collect();
+        assert_eq!(
+            ops,
+            [
+                AssignOp::Recursive,
+                AssignOp::Simple,
+                AssignOp::Conditional,
+                AssignOp::Append,
+                AssignOp::Shell,
+            ]
+        );
+        assert_eq!(
+            commands(&parsed, "build"),
+            ["gcc -O2 -Wall -o app", "echo $(shell git rev-parse HEAD)"]
+        );
+    }
+
+    #[test]
+    fn recursive_values_expand_late_and_simple_ones_early() {
+        let parsed = parse_str(
+            "LATE = $(NAME)\n\
+             EARLY := $(NAME)\n\
+             NAME = app\n\
+             show:\n\
+             \techo [$(LATE)] [$(EARLY)]\n",
+        );
+        assert_eq!(commands(&parsed, "show"), ["echo [app] []"]);
+    }
+
+    #[test]
+    fn define_blocks() {
+        let parsed = parse_str(
+            "define BANNER\n\
+             hello\n\
+             world\n\
+             endef\n\
+             show:\n\
+             \t@echo done\n",
+        );
+        let banner = parsed.variables.iter().find(|v| v.name == "BANNER").unwrap();
+        assert_eq!(banner.value, "hello\nworld");
+        assert_eq!(commands(&parsed, "show"), ["@echo done"]);
+    }
+
+    #[test]
+    fn target_specific_variables() {
+        let parsed = parse_str(
+            "MODE = debug\n\
+             release: MODE = release\n\
+             release debug:\n\
+             \techo $(MODE)\n",
+        );
+        let scoped = parsed.variables.iter().find(|v| v.target.is_some()).unwrap();
+        assert_eq!(scoped.target.as_deref(), Some("release"));
+        assert_eq!(commands(&parsed, "release"), ["echo release"]);
+        assert_eq!(commands(&parsed, "debug"), ["echo debug"]);
+    }
+
+    #[test]
+    fn includes_expand_variables_and_globs_from_the_working_dir() {
+        let parsed = parse_files(
+            "globs",
+            &[
+                ("build/Makefile", "DIR = mk\ninclude $(DIR)/*.mk\nall:\n\techo all\n"),
+                ("mk/a.mk", "a:\n\techo a\n"),
+                ("mk/b.mk", "b:\n\techo b\n"),
+                ("build/mk/c.mk", "c:\n\techo c\n"),
+            ],
+        );
+        assert_eq!(names(&parsed), ["a", "b", "all"]);
+        assert!(parsed.errors.is_empty());
+    }
+
+    #[test]
+    fn missing_includes_are_reported_and_the_rest_is_kept() {
+        let parsed = parse_files(
+            "missing",
+            &[(
+                "Makefile",
+                "include missing.mk\n-include gone.mk\nsinclude also-gone.mk\nbuild:\n\techo build\n",
+            )],
+        );
+        assert_eq!(names(&parsed), ["build"]);
+        assert_eq!(parsed.errors.len(), 1);
+        assert!(parsed.errors[0].contains("cannot include"), "{}", parsed.errors[0]);
+        assert!(parsed.errors[0].contains("missing.mk"), "{}", parsed.errors[0]);
+    }
+
+    #[test]
+    fn include_cycles_are_read_once() {
+        let parsed = parse_files(
+            "cycle",
+            &[
+                ("Makefile", "include a.mk\nmain:\n\techo main\n"),
+                ("a.mk", "include Makefile\na:\n\techo a\n"),
+            ],
+        );
+        assert_eq!(names(&parsed), ["a", "main"]);
+        assert!(parsed.errors.is_empty());
+    }
+
+    #[test]
+    fn automatic_variables_leave_out_order_only_prerequisites() {
+        let parsed = parse_str(
+            "app: main.o util.o | out\n\
+             \tcc -o $@ $^ # $< $|\n\
+             app: out|logs\n\
+             main.o util.o out logs:\n",
+        );
+        assert_eq!(commands(&parsed, "app"), ["cc -o app main.o util.o out # main.o logs"]);
+    }
+
+    #[test]
+    fn the_stem_is_left_to_make() {
+        let parsed = parse_str("STEM = $*\nclean:\n\trm -f $*.o $(STEM).d\n");
+        assert_eq!(commands(&parsed, "clean"), ["rm -f $*.o $*.d"]);
+        let task = parsed.tasks.iter().find(|t| t.name == "clean").unwrap();
+        assert_eq!(task.make_functions, ["*"]);
+    }
+}
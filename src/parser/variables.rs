@@ -0,0 +1,332 @@
+use super::{AssignOp, Variable};
+use std::collections::{HashMap, HashSet};
+
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+#[derive(Debug, Clone)]
+struct Value {
+    text: String,
+    recursive: bool,
+    // functions a simply expanded value still holds unevaluated
+    calls: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VariableTable {
+    values: HashMap<String, Value>,
+    command_line: HashSet<String>,
+}
+
+pub struct AutoVars<'a> {
+    pub target: &'a str,
+    pub prerequisites: &'a [String],
+    pub order_only: &'a [String],
+}
+
+impl VariableTable {
+    // variables set on the make command line win over makefile assignments
+    // that are not marked `override`
+    pub fn with_overrides(overrides: &[(String, String)]) -> Self {
+        let mut table = Self::default();
+        for (name, value) in overrides {
+            table.set(name.clone(), value.clone(), false);
+            table.command_line.insert(name.clone());
+        }
+        table
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.values.contains_key(name) || std::env::var_os(name).is_some()
+    }
+
+    pub fn assign(&mut self, variable: &Variable) {
+        let name = self.expand(&variable.name, None);
+        if self.command_line.contains(&name) && !variable.overridden {
+            return;
+        }
+        match variable.op {
+            AssignOp::Recursive => {
+                self.set(name, variable.value.clone(), true);
+            }
+            AssignOp::Simple | AssignOp::PosixSimple => {
+                let mut calls = Vec::new();
+                let value = self.expand_noting_calls(&variable.value, None, &mut calls);
+                self.set_expanded(name, value, calls);
+            }
+            AssignOp::Conditional => {
+                if !self.is_defined(&name) {
+                    self.set(name, variable.value.clone(), true);
+                }
+            }
+            AssignOp::Append => match self.values.get(&name).cloned() {
+                Some(mut existing) => {
+                    let appended = if existing.recursive {
+                        variable.value.clone()
+                    } else {
+                        self.expand_noting_calls(&variable.value, None, &mut existing.calls)
+                    };
+                    existing.text = if existing.text.is_empty() {
+                        appended
+                    } else {
+                        format!("{} {}", existing.text, appended)
+                    };
+                    self.values.insert(name, existing);
+                }
+                None => self.set(name, variable.value.clone(), true),
+            },
+            // shell assignments are not run while parsing; keep them as an
+            // unexpanded `$(shell ...)` call so they stay visible as-is
+            AssignOp::Shell => {
+                let text = format!("$(shell {})", variable.value);
+                self.set_expanded(name, text, vec!["shell".to_string()]);
+            }
+        }
+    }
+
+    fn set(&mut self, name: String, text: String, recursive: bool) {
+        let calls = Vec::new();
+        self.values.insert(name, Value { text, recursive, calls });
+    }
+
+    fn set_expanded(&mut self, name: String, text: String, calls: Vec<String>) {
+        let recursive = false;
+        self.values.insert(name, Value { text, recursive, calls });
+    }
+
+    pub fn expand(&self, text: &str, auto: Option<&AutoVars>) -> String {
+        self.expand_at_depth(text, auto, 0, &mut Vec::new())
+    }
+
+    // like `expand`, also noting the functions that were left unevaluated
+    pub fn expand_noting_calls(
+        &self,
+        text: &str,
+        auto: Option<&AutoVars>,
+        calls: &mut Vec<String>,
+    ) -> String {
+        self.expand_at_depth(text, auto, 0, calls)
+    }
+
+    fn expand_at_depth(
+        &self,
+        text: &str,
+        auto: Option<&AutoVars>,
+        depth: usize,
+        calls: &mut Vec<String>,
+    ) -> String {
+        if depth > MAX_EXPANSION_DEPTH {
+            return text.to_string();
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut chars = text.char_indices().peekable();
+
+        while let Some((pos, c)) = chars.next() {
+            if c != '$' {
+                out.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                None => out.push('$'),
+                Some((_, '$')) => out.push('$'),
+                Some((open_pos, open @ ('(' | '{'))) => {
+                    let close = if open == '(' { ')' } else { '}' };
+                    let Some(end) = matching_close(text, open_pos, open, close) else {
+                        out.push_str(&text[pos..]);
+                        break;
+                    };
+                    let inner = &text[open_pos + 1..end];
+                    let reference = &text[pos..=end];
+                    out.push_str(&self.expand_reference(inner, reference, auto, depth, calls));
+                    while chars.peek().is_some_and(|&(p, _)| p <= end) {
+                        chars.next();
+                    }
+                }
+                Some((name_pos, name)) => {
+                    let reference = &text[pos..name_pos + name.len_utf8()];
+                    let mut buf = [0; 4];
+                    out.push_str(&self.expand_reference(
+                        name.encode_utf8(&mut buf),
+                        reference,
+                        auto,
+                        depth,
+                        calls,
+                    ));
+                }
+            }
+        }
+
+        out
+    }
+
+    fn expand_reference(
+        &self,
+        inner: &str,
+        reference: &str,
+        auto: Option<&AutoVars>,
+        depth: usize,
+        calls: &mut Vec<String>,
+    ) -> String {
+        // function calls (`$(shell ...)`, `$(wildcard ...)`) are left verbatim
+        if inner.contains(char::is_whitespace) {
+            note_call(calls, inner.split_whitespace().next().unwrap_or_default());
+            return reference.to_string();
+        }
+
+        let inner = self.expand_at_depth(inner, auto, depth + 1, calls);
+        let (name, substitution) = match inner.split_once(':') {
+            Some((name, subst)) => (name, subst.split_once('=')),
+            None => (inner.as_str(), None),
+        };
+
+        let value = match (name, auto) {
+            ("@", Some(auto)) => auto.target.to_string(),
+            ("<", Some(auto)) => auto.prerequisites.first().cloned().unwrap_or_default(),
+            ("^" | "+" | "?", Some(auto)) => auto.prerequisites.join(" "),
+            ("|", Some(auto)) => auto.order_only.join(" "),
+            // tasks come from explicit rules, whose stem depends on the
+            // suffixes make knows; leave it to make
+            ("*", Some(_)) => {
+                note_call(calls, "*");
+                return reference.to_string();
+            }
+            ("@" | "<" | "^" | "+" | "?" | "|" | "*", None) => return reference.to_string(),
+            _ => match self.values.get(name) {
+                Some(value) if value.recursive => {
+                    self.expand_at_depth(&value.text, auto, depth + 1, calls)
+                }
+                Some(value) => {
+                    for call in &value.calls {
+                        note_call(calls, call);
+                    }
+                    value.text.clone()
+                }
+                None => std::env::var(name).unwrap_or_default(),
+            },
+        };
+
+        match substitution {
+            Some((from, to)) => value
+                .split_whitespace()
+                .map(|word| substitute_suffix(word, from, to))
+                .collect::<Vec<_>>()
+                .join(" "),
+            None => value,
+        }
+    }
+}
+
+fn note_call(calls: &mut Vec<String>, function: &str) {
+    if !calls.iter().any(|call| call == function) {
+        calls.push(function.to_string());
+    }
+}
+
+// `$(VAR:.c=.o)` and `$(VAR:%.c=%.o)` substitution references
+fn substitute_suffix(word: &str, from: &str, to: &str) -> String {
+    match (from.split_once('%'), to.split_once('%')) {
+        (Some((from_pre, from_post)), Some((to_pre, to_post))) => {
+            match word
+                .strip_prefix(from_pre)
+                .and_then(|w| w.strip_suffix(from_post))
+            {
+                Some(stem) => format!("{to_pre}{stem}{to_post}"),
+                None => word.to_string(),
+            }
+        }
+        _ => match word.strip_suffix(from) {
+            Some(stem) => format!("{stem}{to}"),
+            None => word.to_string(),
+        },
+    }
+}
+
+fn matching_close(text: &str, open_pos: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0usize;
+    for (pos, c) in text[open_pos..].char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(open_pos + pos);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(assignments: &[(&str, AssignOp, &str)]) -> VariableTable {
+        let mut table = VariableTable::default();
+        for (name, op, value) in assignments {
+            table.assign(&Variable {
+                name: name.to_string(),
+                op: *op,
+                value: value.to_string(),
+                exported: false,
+                overridden: false,
+                target: None,
+            });
+        }
+        table
+    }
+
+    fn calls_in(table: &VariableTable, text: &str) -> (String, Vec<String>) {
+        let mut calls = Vec::new();
+        let expanded = table.expand_noting_calls(text, None, &mut calls);
+        (expanded, calls)
+    }
+
+    #[test]
+    fn unevaluated_functions_are_noted() {
+        let table = table(&[
+            ("NOW", AssignOp::Simple, "$(shell date)"),
+            ("SRC", AssignOp::Recursive, "$(wildcard *.c)"),
+            ("REV", AssignOp::Shell, "git rev-parse HEAD"),
+        ]);
+        assert_eq!(calls_in(&table, "echo $(NOW)").1, ["shell"]);
+        assert_eq!(calls_in(&table, "cc $(SRC)").1, ["wildcard"]);
+        assert_eq!(
+            calls_in(&table, "echo $(REV)"),
+            ("echo $(shell git rev-parse HEAD)".to_string(), vec!["shell".to_string()])
+        );
+    }
+
+    #[test]
+    fn shell_substitutions_are_not_make_functions() {
+        let table = table(&[]);
+        assert_eq!(
+            calls_in(&table, "echo $$(date +%s)"),
+            ("echo $(date +%s)".to_string(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn nested_references() {
+        let table = table(&[
+            ("MODE", AssignOp::Simple, "release"),
+            ("FLAGS_release", AssignOp::Simple, "-O2"),
+            ("SRC", AssignOp::Simple, "main.c util.c"),
+        ]);
+        assert_eq!(table.expand("cc $(FLAGS_$(MODE))", None), "cc -O2");
+        assert_eq!(table.expand("${FLAGS_${MODE}}", None), "-O2");
+        assert_eq!(table.expand("$(SRC:.c=.o)", None), "main.o util.o");
+        assert_eq!(table.expand("$(SRC:%.c=build/%.o)", None), "build/main.o build/util.o");
+    }
+
+    #[test]
+    fn self_references_stop_at_the_depth_limit() {
+        let table = table(&[
+            ("LOOP", AssignOp::Recursive, "$(LOOP)"),
+            ("PING", AssignOp::Recursive, "$(PONG)"),
+            ("PONG", AssignOp::Recursive, "$(PING)"),
+        ]);
+        assert_eq!(table.expand("$(LOOP)", None), "$(LOOP)");
+        assert_eq!(table.expand("$(PING)", None), "$(PONG)");
+    }
+}
@@ -0,0 +1,360 @@
+use crate::config::{sorted_entries, ThemeConfig};
+use ratatui::style::{Color, Modifier, Style};
+use std::collections::HashMap;
+
+pub const BUNDLED: [&str; 4] = ["dark", "light", "high-contrast", "no-color"];
+
+// what each part of the screen looks like; widgets ask for a slot rather
+// than a colour so a theme can change all of them at once
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    // task list text
+    pub text: Style,
+    // task names, form labels, the progress gauge
+    pub accent: Style,
+    // the selected task row
+    pub selection: Style,
+    pub params: Style,
+    pub group: Style,
+    pub description: Style,
+    pub deps: Style,
+    // task stdout and text views
+    pub output: Style,
+    pub stderr: Style,
+    pub success: Style,
+    pub failure: Style,
+    pub running: Style,
+    pub background: Style,
+    // empty states and the filter prompt
+    pub warning: Style,
+    // timestamps, hints and other secondary text
+    pub muted: Style,
+    pub border: Style,
+    pub footer: Style,
+}
+
+const SLOTS: [&str; 17] = [
+    "text",
+    "accent",
+    "selection",
+    "params",
+    "group",
+    "description",
+    "deps",
+    "output",
+    "stderr",
+    "success",
+    "failure",
+    "running",
+    "background",
+    "warning",
+    "muted",
+    "border",
+    "footer",
+];
+
+fn fg(color: Color) -> Style {
+    Style::default().fg(color)
+}
+
+impl Theme {
+    fn bundled(name: &str) -> Option<Self> {
+        let dim = Modifier::DIM;
+        let bold = Modifier::BOLD;
+        let theme = match name {
+            "dark" => Self {
+                name: name.to_string(),
+                text: fg(Color::White),
+                accent: fg(Color::Cyan),
+                selection: fg(Color::Black).bg(Color::Cyan),
+                params: fg(Color::Magenta),
+                group: fg(Color::Blue),
+                description: fg(Color::Gray).add_modifier(dim),
+                deps: fg(Color::Yellow).add_modifier(dim),
+                output: fg(Color::Green),
+                stderr: fg(Color::LightRed),
+                success: fg(Color::Green),
+                failure: fg(Color::Red),
+                running: fg(Color::Yellow),
+                background: fg(Color::Blue),
+                warning: fg(Color::Yellow),
+                muted: fg(Color::DarkGray),
+                border: Style::default(),
+                footer: fg(Color::DarkGray),
+            },
+            // the bright colours of the dark theme wash out on a white
+            // background, so these use darker shades from the 256 palette
+            "light" => Self {
+                name: name.to_string(),
+                text: fg(Color::Reset),
+                accent: fg(Color::Indexed(25)),
+                selection: fg(Color::White).bg(Color::Indexed(25)),
+                params: fg(Color::Indexed(90)),
+                group: fg(Color::Indexed(30)),
+                description: fg(Color::Indexed(242)),
+                deps: fg(Color::Indexed(130)),
+                output: fg(Color::Reset),
+                stderr: fg(Color::Indexed(124)),
+                success: fg(Color::Indexed(28)),
+                failure: fg(Color::Indexed(160)),
+                running: fg(Color::Indexed(130)),
+                background: fg(Color::Indexed(30)),
+                warning: fg(Color::Indexed(130)),
+                muted: fg(Color::Indexed(244)),
+                border: fg(Color::Indexed(244)),
+                footer: fg(Color::Indexed(242)),
+            },
+            "high-contrast" => Self {
+                name: name.to_string(),
+                text: fg(Color::White),
+                accent: fg(Color::LightCyan).add_modifier(bold),
+                selection: fg(Color::Black).bg(Color::LightYellow),
+                params: fg(Color::LightMagenta),
+                group: fg(Color::LightCyan),
+                description: fg(Color::White),
+                deps: fg(Color::LightYellow),
+                output: fg(Color::White),
+                stderr: fg(Color::LightRed).add_modifier(bold),
+                success: fg(Color::LightGreen).add_modifier(bold),
+                failure: fg(Color::LightRed).add_modifier(bold),
+                running: fg(Color::LightYellow).add_modifier(bold),
+                background: fg(Color::LightCyan).add_modifier(bold),
+                warning: fg(Color::LightYellow).add_modifier(bold),
+                muted: fg(Color::Gray),
+                border: fg(Color::White),
+                footer: fg(Color::White),
+            },
+            // https://no-color.org: only bold, dim and reverse video
+            "no-color" => Self {
+                name: name.to_string(),
+                text: Style::default(),
+                accent: Style::default(),
+                selection: Style::default().add_modifier(Modifier::REVERSED),
+                params: Style::default(),
+                group: Style::default(),
+                description: Style::default().add_modifier(dim),
+                deps: Style::default().add_modifier(dim),
+                output: Style::default(),
+                stderr: Style::default().add_modifier(bold),
+                success: Style::default(),
+                failure: Style::default().add_modifier(bold),
+                running: Style::default().add_modifier(bold),
+                background: Style::default(),
+                warning: Style::default().add_modifier(bold),
+                muted: Style::default().add_modifier(dim),
+                border: Style::default(),
+                footer: Style::default().add_modifier(dim),
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+
+    // `theme = "light"` picks a bundled theme or a `[themes.<name>]` table;
+    // without it `NO_COLOR` turns colours off. problems are reported and the
+    // dark theme is used instead
+    pub fn new(name: Option<&str>, themes: &HashMap<String, ThemeConfig>) -> (Self, Vec<String>) {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self::choose(name, themes, no_color)
+    }
+
+    fn choose(name: Option<&str>, themes: &HashMap<String, ThemeConfig>, no_color: bool) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+        let name = match name {
+            Some(name) if Self::bundled(name).is_some() || themes.contains_key(name) => name,
+            Some(name) => {
+                errors.push(format!(
+                    "theme: `{name}` is not a theme, expected one of {} or a [themes.{name}] table",
+                    BUNDLED.join(", ")
+                ));
+                "dark"
+            }
+            None if no_color => "no-color",
+            None => "dark",
+        };
+
+        let Some(custom) = themes.get(name) else {
+            return (Self::bundled(name).unwrap_or_else(Self::dark), errors);
+        };
+        // a table named after a bundled theme adjusts that theme
+        let base = custom.base.as_deref().unwrap_or(if Self::bundled(name).is_some() { name } else { "dark" });
+        let mut theme = Self::bundled(base).unwrap_or_else(|| {
+            errors.push(format!(
+                "themes.{name}.base: `{base}` is not a bundled theme, expected one of {}",
+                BUNDLED.join(", ")
+            ));
+            Self::dark()
+        });
+        theme.name = name.to_string();
+
+        for (slot, value) in sorted_entries(&custom.slots) {
+            let Some(target) = theme.slot_mut(slot) else {
+                errors.push(format!(
+                    "themes.{name}.{slot}: unknown slot, expected one of {}",
+                    SLOTS.join(", ")
+                ));
+                continue;
+            };
+            match parse_style(value) {
+                Some(style) => *target = style,
+                None => errors.push(format!(
+                    "themes.{name}.{slot}: `{value}` is not a style, e.g. \"bold yellow\" or \"#000000 on #ffd75f\""
+                )),
+            }
+        }
+        (theme, errors)
+    }
+
+    fn dark() -> Self {
+        Self::bundled("dark").expect("the dark theme is bundled")
+    }
+
+    fn slot_mut(&mut self, slot: &str) -> Option<&mut Style> {
+        Some(match slot {
+            "text" => &mut self.text,
+            "accent" => &mut self.accent,
+            "selection" => &mut self.selection,
+            "params" => &mut self.params,
+            "group" => &mut self.group,
+            "description" => &mut self.description,
+            "deps" => &mut self.deps,
+            "output" => &mut self.output,
+            "stderr" => &mut self.stderr,
+            "success" => &mut self.success,
+            "failure" => &mut self.failure,
+            "running" => &mut self.running,
+            "background" => &mut self.background,
+            "warning" => &mut self.warning,
+            "muted" => &mut self.muted,
+            "border" => &mut self.border,
+            "footer" => &mut self.footer,
+            _ => return None,
+        })
+    }
+
+    // the selection colours with the modifiers of the slot underneath, so a
+    // dimmed description stays dimmed on the selected row
+    pub fn selected(&self, slot: Style) -> Style {
+        self.selection.add_modifier(slot.add_modifier)
+    }
+}
+
+// words in any order: modifiers, a foreground colour, and `on` followed by a
+// background colour, e.g. `bold yellow on #303030`
+fn parse_style(text: &str) -> Option<Style> {
+    let mut style = Style::default();
+    let mut words = text.split_whitespace();
+    let mut parsed_any = false;
+    while let Some(word) = words.next() {
+        parsed_any = true;
+        let modifier = match word.to_ascii_lowercase().as_str() {
+            "bold" => Modifier::BOLD,
+            "dim" => Modifier::DIM,
+            "italic" => Modifier::ITALIC,
+            "underlined" | "underline" => Modifier::UNDERLINED,
+            "reversed" | "reverse" => Modifier::REVERSED,
+            "on" => {
+                style = style.bg(words.next()?.parse().ok()?);
+                continue;
+            }
+            "default" => {
+                style = style.fg(Color::Reset);
+                continue;
+            }
+            _ => {
+                style = style.fg(word.parse().ok()?);
+                continue;
+            }
+        };
+        style = style.add_modifier(modifier);
+    }
+    parsed_any.then_some(style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn themes(name: &str, entries: &[(&str, &str)]) -> HashMap<String, ThemeConfig> {
+        let mut config = ThemeConfig::default();
+        for (key, value) in entries {
+            match *key {
+                "base" => config.base = Some(value.to_string()),
+                slot => {
+                    config.slots.insert(slot.to_string(), value.to_string());
+                }
+            }
+        }
+        HashMap::from([(name.to_string(), config)])
+    }
+
+    #[test]
+    fn styles_are_words_in_any_order() {
+        let expected = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        assert_eq!(parse_style("bold yellow underlined"), Some(expected));
+        assert_eq!(parse_style("yellow underline BOLD"), Some(expected));
+        assert_eq!(parse_style("default"), Some(Style::default().fg(Color::Reset)));
+        assert_eq!(parse_style("217"), Some(Style::default().fg(Color::Indexed(217))));
+    }
+
+    #[test]
+    fn backgrounds_follow_on() {
+        assert_eq!(
+            parse_style("#000000 on #ffd75f"),
+            Some(Style::default().fg(Color::Rgb(0, 0, 0)).bg(Color::Rgb(0xff, 0xd7, 0x5f)))
+        );
+        assert_eq!(parse_style("on blue bold"), Some(Style::default().bg(Color::Blue).add_modifier(Modifier::BOLD)));
+        assert_eq!(parse_style("bold on"), None);
+        assert_eq!(parse_style("on nope"), None);
+        assert_eq!(parse_style("sparkly"), None);
+        assert_eq!(parse_style("  "), None);
+    }
+
+    #[test]
+    fn unknown_slots_and_bad_styles_are_reported() {
+        let themes = themes("mine", &[("stdrr", "red"), ("stderr", "very red"), ("accent", "magenta")]);
+        let (theme, errors) = Theme::choose(Some("mine"), &themes, false);
+        assert_eq!(theme.name, "mine");
+        assert_eq!(theme.accent, Style::default().fg(Color::Magenta));
+        assert_eq!(theme.stderr, Theme::dark().stderr);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("themes.mine.stderr: `very red` is not a style"), "{}", errors[0]);
+        assert!(errors[1].starts_with("themes.mine.stdrr: unknown slot"), "{}", errors[1]);
+    }
+
+    #[test]
+    fn a_bad_base_falls_back_to_dark() {
+        let themes = themes("mine", &[("base", "solarized")]);
+        let (theme, errors) = Theme::choose(Some("mine"), &themes, false);
+        assert_eq!(theme.text, Theme::dark().text);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("themes.mine.base: `solarized` is not a bundled theme"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn a_table_named_after_a_bundled_theme_adjusts_it() {
+        let light = Theme::bundled("light").unwrap();
+        let themes = themes("light", &[("accent", "red")]);
+        let (theme, errors) = Theme::choose(Some("light"), &themes, false);
+        assert!(errors.is_empty());
+        assert_eq!(theme.name, "light");
+        assert_eq!(theme.accent, Style::default().fg(Color::Red));
+        assert_eq!(theme.text, light.text);
+        assert_eq!(theme.selection, light.selection);
+    }
+
+    #[test]
+    fn no_color_applies_only_without_a_configured_theme() {
+        let none = HashMap::new();
+        assert_eq!(Theme::choose(None, &none, true).0.name, "no-color");
+        assert_eq!(Theme::choose(None, &none, false).0.name, "dark");
+        assert_eq!(Theme::choose(Some("light"), &none, true).0.name, "light");
+
+        let (theme, errors) = Theme::choose(Some("nope"), &none, true);
+        assert_eq!(theme.name, "dark");
+        assert!(errors[0].starts_with("theme: `nope` is not a theme"), "{}", errors[0]);
+    }
+}
@@ -1,25 +1,36 @@
-use crate::app::App;
-use crate::executor::Executor;
+use crate::app::{App, Launch, ScrollState, MAX_SHOWN_LINES};
+use crate::executor::{ExecutionMode, Executor, OutputLine, Stream};
+use crate::keymap::{format_sequence, Action, Keymap};
+use crate::theme::Theme;
+use crate::params::{Arguments, FieldKind, ParamForm};
+use crate::parser::{join_dependencies, Task};
 use anyhow::Result;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
+    event::{self, Event, KeyCode},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph, Wrap},
     Frame, Terminal,
 };
 use std::io;
+use std::time::Duration;
 
 enum InputMode {
     Normal,
     Filter,
     Params,
+    // tasks are still running and quitting would orphan them
+    ConfirmQuit,
+    // browsing past runs in the lower panel
+    History,
+    // typing a task name to narrow the history list
+    HistoryFilter,
 }
 
 pub async fn run(app: &mut App) -> Result<()> {
@@ -31,7 +42,7 @@ pub async fn run(app: &mut App) -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
 
-    let executor = Executor::new(app.working_dir.clone());
+    let executor = Executor::new(app.working_dir.clone(), app.grace);
     let result = event_loop(&mut terminal, app, &executor).await;
 
     disable_raw_mode()?;
@@ -47,13 +58,22 @@ async fn event_loop(
     executor: &Executor,
 ) -> Result<()> {
     let mut mode = InputMode::Normal;
+    // quitting once every stopped task has exited
+    let mut stopping = false;
 
     loop {
-        let in_filter_mode = matches!(mode, InputMode::Filter);
-        let in_param_mode = matches!(mode, InputMode::Params);
-        terminal.draw(|f| ui(f, app, in_filter_mode, in_param_mode))?;
+        app.drain_run_events();
+        if stopping && !app.is_running() {
+            break;
+        }
+        for (task_name, arguments, background) in app.take_restarts() {
+            if let Some(task) = app.tasks.iter().find(|t| t.name == task_name).cloned() {
+                run_task(app, executor, &task, &arguments, background);
+            }
+        }
+        terminal.draw(|f| ui(f, app, &mode))?;
 
-        if crossterm::event::poll(std::time::Duration::from_millis(100))? {
+        if crossterm::event::poll(Duration::from_millis(100))? {
             if let Event::Key(key) = event::read()? {
                 match mode {
                     InputMode::Filter => {
@@ -79,113 +99,246 @@ async fn event_loop(
                         }
                     }
                     InputMode::Params => {
+                        let Some(form) = app.param_form.as_mut() else {
+                            mode = InputMode::Normal;
+                            continue;
+                        };
                         match key.code {
                             KeyCode::Esc => {
                                 mode = InputMode::Normal;
-                                app.param_input.clear();
+                                app.param_form = None;
                             }
+                            KeyCode::Tab | KeyCode::Down => form.focus_next(),
+                            KeyCode::BackTab | KeyCode::Up => form.focus_prev(),
                             KeyCode::Enter => {
+                                let task = app.tasks.iter().find(|t| t.name == form.task_name);
+                                if let Some(task) = task.cloned() {
+                                    match form.validate(&task) {
+                                        Ok(arguments) => {
+                                            mode = InputMode::Normal;
+                                            let launch = form.launch;
+                                            app.param_form = None;
+                                            launch_task(terminal, app, executor, &task, &arguments, launch).await?;
+                                        }
+                                        Err(message) => form.error = Some(message),
+                                    }
+                                }
+                            }
+                            KeyCode::Char(c) => form.push_char(c),
+                            KeyCode::Backspace => form.pop_char(),
+                            _ => {}
+                        }
+                    }
+                    InputMode::ConfirmQuit => match key.code {
+                        KeyCode::Char('y') | KeyCode::Enter => {
+                            mode = InputMode::Normal;
+                            app.stop_all_runs();
+                            stopping = true;
+                            app.show_text(format!("stopping {} task(s) before quitting...", app.active_count()));
+                        }
+                        KeyCode::Char('n') | KeyCode::Esc => mode = InputMode::Normal,
+                        _ => {}
+                    },
+                    InputMode::History => match app.keymap.feed(&mut app.pending_keys, key) {
+                        Some(Action::Quit | Action::History) => mode = InputMode::Normal,
+                        Some(Action::Up) => app.move_history_selection(-1),
+                        Some(Action::Down) => app.move_history_selection(1),
+                        Some(Action::PageUp) => app.move_history_selection(-5),
+                        Some(Action::PageDown) => app.move_history_selection(5),
+                        Some(Action::Top) => app.move_history_selection(isize::MIN),
+                        Some(Action::Bottom) => app.move_history_selection(isize::MAX),
+                        Some(Action::Filter) => mode = InputMode::HistoryFilter,
+                        Some(Action::HistoryFilterOutcome) => {
+                            app.history_view.outcome = app.history_view.outcome.next();
+                            app.clamp_history_selection();
+                        }
+                        Some(Action::Run) if app.selected_history_entry().is_some() => {
+                            mode = InputMode::Normal;
+                            app.open_selected_history_entry();
+                        }
+                        Some(Action::HistoryRerun) => {
+                            if let Some(entry) = app.selected_history_entry() {
+                                let task = app.tasks.iter().find(|t| t.name == entry.task_name).cloned();
                                 mode = InputMode::Normal;
+                                match task {
+                                    Some(task) => {
+                                        let arguments = entry.arguments.to_arguments(&task);
+                                        let launch = app.default_launch(&task);
+                                        launch_task(terminal, app, executor, &task, &arguments, launch).await?;
+                                    }
+                                    None => {
+                                        let message = format!("task `{}` no longer exists", entry.task_name);
+                                        app.show_text(message);
+                                    }
+                                }
                             }
-                            KeyCode::Char(c) if app.param_input.len() < 100 => {
-                                app.param_input.push(c);
+                        }
+                        _ => {}
+                    },
+                    InputMode::HistoryFilter => {
+                        match key.code {
+                            KeyCode::Esc => {
+                                app.history_view.filter.clear();
+                                mode = InputMode::History;
                             }
+                            KeyCode::Enter => mode = InputMode::History,
+                            KeyCode::Char(c) if app.history_view.filter.len() < 50 => app.history_view.filter.push(c),
                             KeyCode::Backspace => {
-                                app.param_input.pop();
+                                app.history_view.filter.pop();
                             }
                             _ => {}
                         }
+                        app.clamp_history_selection();
                     }
                     InputMode::Normal => {
-                        match key.code {
-                            KeyCode::Char('q') | KeyCode::Esc => break,
-                            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
-                            KeyCode::Up => app.move_selection_up(),
-                            KeyCode::Down => app.move_selection_down(),
-                            KeyCode::PageUp => {
+                        let Some(action) = app.keymap.feed(&mut app.pending_keys, key) else {
+                            continue;
+                        };
+                        match action {
+                            Action::Quit => {
+                                if !app.is_running() {
+                                    break;
+                                }
+                                mode = InputMode::ConfirmQuit;
+                            }
+                            Action::Cancel => {
+                                let cancelled = app.cancel_viewed_run();
+                                if !cancelled && !app.is_running() {
+                                    break;
+                                }
+                                if !cancelled {
+                                    mode = InputMode::ConfirmQuit;
+                                }
+                            }
+                            Action::Up => app.move_selection_up(),
+                            Action::Down => app.move_selection_down(),
+                            Action::PageUp => {
                                 if app.show_output {
                                     app.scroll_output_up(5);
                                 } else {
                                     app.page_up(5);
                                 }
                             }
-                            KeyCode::PageDown => {
+                            Action::PageDown => {
                                 if app.show_output {
                                     app.scroll_output_down(5);
                                 } else {
                                     app.page_down(5);
                                 }
                             }
-                            KeyCode::Home => app.goto_top(),
-                            KeyCode::End => app.goto_bottom(),
-                            KeyCode::Char('/') => {
+                            Action::Top => app.goto_top(),
+                            Action::Bottom => app.goto_bottom(),
+                            Action::Filter => {
                                 mode = InputMode::Filter;
                                 app.update_filter(String::new());
                             }
-                            KeyCode::Char('p') => {
-                                mode = InputMode::Params;
-                            }
-                            KeyCode::Enter => {
+                            Action::Params => {
                                 if let Some(task) = app.selected_task() {
-                                    let task_name = task.name.clone();
-                                    let base_commands = task.commands.clone();
-                                    let commands = if app.param_input.trim().is_empty() {
-                                        base_commands
-                                    } else {
-                                        base_commands
-                                            .into_iter()
-                                            .map(|c| format!("{} {}", c, app.param_input))
-                                            .collect()
+                                    app.param_form = Some(app.param_form(task, app.default_launch(task)));
+                                    mode = InputMode::Params;
+                                }
+                            }
+                            Action::Run
+                                if app
+                                    .selected_task()
+                                    .is_some_and(|task| app.collapsed_module(task).is_some()) =>
+                            {
+                                app.toggle_selected_module();
+                            }
+                            Action::Run | Action::RunInteractive | Action::RunBackground => {
+                                if let Some(task) = app.selected_task().cloned() {
+                                    let launch = match action {
+                                        Action::RunInteractive => Launch::Interactive,
+                                        Action::RunBackground => Launch::Background,
+                                        _ => app.default_launch(&task),
                                     };
-
-                                    match executor.execute_task(&task_name, &commands).await {
-                                        Ok((exit_code, output)) => {
-                                            app.add_to_history(task_name, exit_code, output.clone());
-                                            app.current_output = output;
-                                            app.output_scroll = 0;
-                                            app.show_output = true;
-                                        }
-                                        Err(e) => {
-                                            app.current_output = format!("Error: {}", e);
-                                            app.output_scroll = 0;
-                                            app.show_output = true;
-                                        }
+                                    // recipes with required parameters go through the form first
+                                    if task.parameters.iter().any(|p| p.is_required()) {
+                                        app.param_form = Some(app.param_form(&task, launch));
+                                        mode = InputMode::Params;
+                                    } else {
+                                        launch_task(terminal, app, executor, &task, &Arguments::default(), launch).await?;
+                                    }
+                                }
+                            }
+                            Action::Stop => {
+                                if let Some(task) = app.selected_task() {
+                                    let name = task.name.clone();
+                                    if !app.stop_run(&name) {
+                                        app.show_text(format!("task `{name}` is not running"));
+                                    }
+                                }
+                            }
+                            Action::Restart => {
+                                if let Some(task) = app.selected_task().cloned() {
+                                    if !app.restart_run(&task.name) {
+                                        let launch = app.default_launch(&task);
+                                        launch_task(terminal, app, executor, &task, &Arguments::default(), launch).await?;
                                     }
                                 }
                             }
-                            KeyCode::Char('o') => {
-                                app.show_output = !app.show_output;
+                            Action::Attach => {
+                                if let Some(task) = app.selected_task() {
+                                    let name = task.name.clone();
+                                    if !app.attach_run(&name) {
+                                        app.show_text(format!("task `{name}` has not been run yet"));
+                                    }
+                                }
                             }
-                            KeyCode::Char('h') => {
-                                if !app.task_history.is_empty() {
-                                    let history_text = app
-                                        .task_history
-                                        .iter()
-                                        .rev()
-                                        .take(10)
-                                        .map(|e| format!("{} - {} (exit: {})", e.timestamp, e.task_name, e.exit_code))
-                                        .collect::<Vec<_>>()
-                                        .join("\n");
-                                    app.current_output = history_text;
-                                    app.output_scroll = 0;
-                                    app.show_output = true;
+                            Action::ToggleModule => app.toggle_selected_module(),
+                            Action::ToggleMode => {
+                                app.execution_mode = app.execution_mode.toggle();
+                            }
+                            Action::TogglePty => app.use_pty = !app.use_pty,
+                            Action::ToggleOutput => {
+                                if app.showing_text && !app.runs.is_empty() {
+                                    app.show_run(app.selected_run);
+                                } else {
+                                    app.show_output = !app.show_output;
                                 }
                             }
-                            KeyCode::Char('g') => {
+                            Action::NextTab => app.next_run(),
+                            Action::PrevTab => app.prev_run(),
+                            Action::CloseTab => app.close_viewed_run(),
+                            Action::StderrOnly => app.stderr_only = !app.stderr_only,
+                            Action::History => {
+                                if app.history.entries.is_empty() {
+                                    app.show_text("no runs recorded yet".to_string());
+                                } else {
+                                    app.history_view.selected = 0;
+                                    mode = InputMode::History;
+                                }
+                            }
+                            Action::Graph => {
                                 if let Some(task) = app.selected_task() {
                                     let tree = app.dependency_tree(&task.name);
                                     let header = format!("dependency graph for {}:\n\n", task.name);
-                                    app.current_output = header + &tree;
-                                    app.output_scroll = 0;
-                                    app.show_output = true;
+                                    app.show_text(header + &tree);
                                 }
                             }
-                            KeyCode::Char('?') => {
-                                app.current_output = get_help_text();
-                                app.output_scroll = 0;
-                                app.show_output = true;
+                            Action::Details => {
+                                if let Some(task) = app.selected_task() {
+                                    let details = app.task_details(task);
+                                    app.show_text(details);
+                                }
                             }
-                            _ => {}
+                            Action::Variables => {
+                                let listing = if app.variables.is_empty() {
+                                    "no variables defined".to_string()
+                                } else {
+                                    format!("variables:\n\n{}", app.variable_listing())
+                                };
+                                app.show_text(listing);
+                            }
+                            Action::Help => {
+                                let mut help = get_help_text(&app.keymap);
+                                if !app.config_errors.is_empty() {
+                                    help = format!("{}\n\n{}", app.config_error_text(), help);
+                                }
+                                app.show_text(help);
+                            }
+                            // only mean something in the history view
+                            Action::HistoryFilterOutcome | Action::HistoryRerun => {}
                         }
                     }
                 }
@@ -196,41 +349,143 @@ async fn event_loop(
     Ok(())
 }
 
-fn get_help_text() -> String {
-    "LazyMake - Interactive Make/Justfile TUI\n\n\
-     KEYBINDINGS:\n\
-     ↑/↓       Navigate tasks\n\
-     /         Start filtering (fuzzy search)\n\
-     p         Edit task parameters\n\
-     Esc       Cancel filter/param input\n\
-     Enter     Execute selected task\n\
-     g         Show dependency graph for task\n\
-     PageUp    Page up (task list or output)\n\
-     PageDown  Page down (task list or output)\n\
-     Home      Jump to first task\n\
-     End       Jump to last task\n\
-     o         Toggle output panel\n\
-     h         Show task history\n\
-     ?         Show this help\n\
-     q/Esc     Quit\n\n\
-     FEATURES:\n\
-     • Browse all tasks with descriptions\n\
-     • Fuzzy search filtering\n\
-     • View task dependencies and graph\n\
-     • Execute tasks with live output\n\
-     • Task execution history\n\
-     • Interactive parameter input\n\
-     • Support for Makefile and Justfile"
-        .to_string()
+async fn launch_task(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+    executor: &Executor,
+    task: &Task,
+    arguments: &Arguments,
+    launch: Launch,
+) -> Result<()> {
+    match launch {
+        Launch::Interactive => run_interactive(terminal, app, executor, task, arguments).await?,
+        Launch::Background => run_task(app, executor, task, arguments, true),
+        Launch::Focused => run_task(app, executor, task, arguments, false),
+    }
+    Ok(())
+}
+
+fn run_task(app: &mut App, executor: &Executor, task: &Task, arguments: &Arguments, background: bool) {
+    if let Some(run) = app.find_active_run(&task.name) {
+        let message = format!("task `{}` is still running", run.task_name);
+        app.show_text(message);
+        return;
+    }
+
+    match app.invocations(task, arguments) {
+        Ok(invocations) => {
+            let handle = executor.start(invocations);
+            app.start_run(task.name.clone(), arguments.clone(), background, handle);
+        }
+        Err(e) => app.show_text(format!("Error: {}", e)),
+    }
+}
+
+// leaves the TUI for the duration of the task so it can use the terminal,
+// then waits for a key before coming back
+async fn run_interactive(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+    executor: &Executor,
+    task: &Task,
+    arguments: &Arguments,
+) -> Result<()> {
+    let invocations = match app.invocations(task, arguments) {
+        Ok(invocations) => invocations,
+        Err(e) => {
+            app.show_text(format!("Error: {}", e));
+            return Ok(());
+        }
+    };
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    let started = chrono::Local::now();
+    let status = executor.run_attached(&invocations).await;
+    println!("{} ({}), press any key to return to lazymake", task.name, status);
+
+    enable_raw_mode()?;
+    loop {
+        if let Event::Key(_) = event::read()? {
+            break;
+        }
+    }
+    execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+    terminal.clear()?;
+
+    let note = "ran interactively; output went to the terminal".to_string();
+    let output = [OutputLine::new(Stream::Info, note)];
+    let mut message = format!("{} ran interactively ({})", task.name, status);
+    if let Err(e) = app.add_to_history(task.name.clone(), arguments, started, status, &output) {
+        message.push_str(&format!("\nhistory not saved: {e}"));
+    }
+    app.show_text(message);
+    Ok(())
+}
+
+// the key list comes from the active keymap; the text inputs add a few
+// keys of their own
+fn get_help_text(keymap: &Keymap) -> String {
+    format!(
+        "LazyMake - Interactive Make/Justfile TUI\n\n\
+         KEYBINDINGS ({} preset, keys first, then the action name for [keys] in the config):\n\
+         {}\n\n\
+         Keys separated by spaces form a chord, pressed one after another.\n\n\
+         IN FORMS AND FILTERS:\n\
+         Tab       Next parameter field (Shift+Tab previous)\n\
+         Enter     Confirm\n\
+         Esc       Cancel filter/param input\n\n\
+         IN THE HISTORY:\n\
+         {} filter by task, {} by outcome, {} reopen the output,\n\
+         {} re-run with the same parameters, {} back\n\n\
+         FEATURES:\n\
+         • Browse all tasks with descriptions\n\
+         • Fuzzy search filtering\n\
+         • View task dependencies and graph\n\
+         • Execute tasks with live output\n\
+         • Task execution history\n\
+         • Parameter form generated from recipe signatures\n\
+         • Support for Makefile and Justfile",
+        keymap.preset,
+        keymap.help_lines().join("\n"),
+        keymap.hint(Action::Filter),
+        keymap.hint(Action::HistoryFilterOutcome),
+        keymap.hint(Action::Run),
+        keymap.hint(Action::HistoryRerun),
+        keymap.hint(Action::Quit),
+    )
 }
 
-fn ui(f: &mut Frame, app: &App, in_filter_mode: bool, in_param_mode: bool) {
+fn key_hints(keymap: &Keymap) -> String {
+    let navigate = format!("{}/{} Navigate", keymap.hint(Action::Up), keymap.hint(Action::Down));
+    let hints = [
+        (Action::Filter, "Filter"),
+        (Action::Params, "Params"),
+        (Action::Run, "Run"),
+        (Action::Details, "Details"),
+        (Action::Graph, "Graph"),
+        (Action::Variables, "Vars"),
+        (Action::ToggleOutput, "Output"),
+        (Action::History, "History"),
+        (Action::Help, "Help"),
+        (Action::Quit, "Quit"),
+    ]
+    .map(|(action, label)| format!("{} {label}", keymap.hint(action)));
+    std::iter::once(navigate).chain(hints).collect::<Vec<_>>().join(" | ")
+}
+
+fn ui(f: &mut Frame, app: &mut App, mode: &InputMode) {
     if !app.has_tasks() {
-        draw_empty_state(f);
+        draw_empty_state(f, &app.theme);
         return;
     }
 
-    let constraints = if app.show_output {
+    let in_filter_mode = matches!(mode, InputMode::Filter);
+    let in_param_mode = matches!(mode, InputMode::Params);
+    let in_history = matches!(mode, InputMode::History | InputMode::HistoryFilter);
+    let constraints = if app.show_output || in_history {
         vec![
             Constraint::Percentage(50),
             Constraint::Percentage(50),
@@ -247,18 +502,215 @@ fn ui(f: &mut Frame, app: &App, in_filter_mode: bool, in_param_mode: bool) {
 
     draw_task_list(f, app, chunks[0], in_filter_mode, in_param_mode);
 
-    if app.show_output {
+    if in_history {
+        draw_history(f, app, chunks[1], matches!(mode, InputMode::HistoryFilter));
+    } else if app.show_output {
         draw_output_panel(f, app, chunks[1]);
     }
+
+    if let Some(form) = &app.param_form {
+        draw_param_form(f, form, &app.theme);
+    }
+
+    if matches!(mode, InputMode::ConfirmQuit) {
+        draw_quit_prompt(f, app);
+    }
+}
+
+fn draw_history(f: &mut Frame, app: &App, area: Rect, in_filter_mode: bool) {
+    let theme = &app.theme;
+    let entries = app.history_entries();
+    let items: Vec<ListItem> = entries
+        .iter()
+        .map(|entry| {
+            let (badge, style) = if entry.status.is_success() {
+                ("✓", theme.success)
+            } else {
+                ("✗", theme.failure)
+            };
+            let mut spans = vec![
+                Span::styled(format!("{badge} "), style),
+                Span::styled(entry.started.format("%Y-%m-%d %H:%M:%S  ").to_string(), theme.muted),
+                Span::styled(entry.task_name.clone(), theme.accent),
+                Span::raw(format!("  {} ({:.1}s)", entry.status, entry.duration.as_secs_f64())),
+            ];
+            let arguments = entry.arguments.to_string();
+            if !arguments.is_empty() {
+                spans.push(Span::styled(format!("  {arguments}"), theme.params));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let view = &app.history_view;
+    let mut title = format!(" history ({} of {} runs) · {}", entries.len(), app.history.entries.len(), view.outcome.label());
+    if in_filter_mode {
+        title.push_str(&format!(" · task: {}_ ", view.filter));
+    } else if !view.filter.is_empty() {
+        title.push_str(&format!(" · task: {} ", view.filter));
+    } else {
+        title.push(' ');
+    }
+    let block = Block::default()
+        .title(title)
+        .title_bottom(format!(
+            " {} task · {} outcome · {} open · {} re-run · {} back ",
+            app.keymap.hint(Action::Filter),
+            app.keymap.hint(Action::HistoryFilterOutcome),
+            app.keymap.hint(Action::Run),
+            app.keymap.hint(Action::HistoryRerun),
+            app.keymap.hint(Action::Quit)
+        ))
+        .borders(Borders::ALL)
+        .border_style(theme.border);
+
+    if entries.is_empty() {
+        let paragraph = Paragraph::new("no runs match the current filter")
+            .block(block)
+            .alignment(Alignment::Center)
+            .style(theme.warning);
+        f.render_widget(paragraph, area);
+        return;
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(view.selected));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_quit_prompt(f: &mut Frame, app: &App) {
+    let running: Vec<&str> = app
+        .runs
+        .iter()
+        .filter(|run| run.is_active())
+        .map(|run| run.task_name.as_str())
+        .collect();
+    let area = f.size();
+    let height = u16::try_from(running.len()).unwrap_or(u16::MAX).saturating_add(6).min(area.height.saturating_sub(2));
+    let popup = Rect {
+        x: area.width / 4,
+        y: area.height.saturating_sub(height) / 2,
+        width: area.width / 2,
+        height,
+    };
+
+    let mut lines = vec![Line::from("these tasks are still running:"), Line::from("")];
+    lines.extend(
+        running
+            .iter()
+            .map(|name| Line::from(Span::styled(format!("  ● {name}"), app.theme.running))),
+    );
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "y stop them and quit, n keep working",
+        app.theme.muted,
+    )));
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title(" quit ")
+            .borders(Borders::ALL)
+            .border_style(app.theme.border),
+    );
+    f.render_widget(Clear, popup);
+    f.render_widget(paragraph, popup);
+}
+
+fn draw_param_form(f: &mut Frame, form: &ParamForm, theme: &Theme) {
+    let area = f.size();
+    let height = u16::try_from(form.fields.len()).unwrap_or(u16::MAX).saturating_add(4).min(area.height.saturating_sub(2));
+    let popup = Rect {
+        x: area.width / 6,
+        y: area.height.saturating_sub(height) / 2,
+        width: area.width * 2 / 3,
+        height,
+    };
+
+    // keep the focused field visible when there are more fields than rows
+    let visible = (height as usize).saturating_sub(4).max(1);
+    let first = form.focused.saturating_sub(visible - 1);
+
+    let mut lines: Vec<Line> = form
+        .fields
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .map(|(idx, field)| {
+            let focused = idx == form.focused;
+            let kind = match field.kind {
+                FieldKind::Required => "required",
+                FieldKind::Optional => "optional",
+                FieldKind::Variadic { required: true } => "one or more",
+                FieldKind::Variadic { required: false } => "zero or more",
+                FieldKind::Override => "variable",
+                FieldKind::Overrides => "NAME=value",
+                FieldKind::Timeout => "duration",
+            };
+
+            let mut spans = vec![
+                Span::styled(
+                    format!("{} {} ", if focused { ">" } else { " " }, field.label),
+                    if focused {
+                        theme.accent.add_modifier(Modifier::BOLD)
+                    } else {
+                        theme.accent
+                    },
+                ),
+                Span::styled(format!("({kind}) "), theme.muted),
+                Span::raw(field.value.clone()),
+            ];
+            if focused {
+                spans.push(Span::raw("_"));
+            }
+            if field.value.is_empty() && !field.hint.is_empty() {
+                spans.push(Span::styled(
+                    format!(" {}", field.hint),
+                    theme.muted.add_modifier(Modifier::DIM),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect();
+
+    if form.fields.iter().all(|field| field.kind == FieldKind::Timeout) {
+        lines.push(Line::from(Span::styled(
+            "this recipe takes no parameters",
+            theme.muted,
+        )));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(match &form.error {
+        Some(error) => Line::from(Span::styled(error.clone(), theme.failure)),
+        None => Line::from(Span::styled("Enter to run, Esc to cancel", theme.muted)),
+    });
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title(format!(" parameters: {} ", form.task_name))
+            .borders(Borders::ALL)
+            .border_style(theme.border),
+    );
+
+    f.render_widget(Clear, popup);
+    f.render_widget(paragraph, popup);
 }
 
-fn draw_empty_state(f: &mut Frame) {
+fn draw_empty_state(f: &mut Frame, theme: &Theme) {
     let area = f.size();
     let message = "no tasks found\n\nmake sure you have a makefile or justfile in this directory\n\npress 'q' to quit";
     
     let paragraph = Paragraph::new(message)
-        .block(Block::default().title(" lazymake ").borders(Borders::ALL))
-        .style(Style::default().fg(Color::Yellow))
+        .block(
+            Block::default()
+                .title(" lazymake ")
+                .borders(Borders::ALL)
+                .border_style(theme.border),
+        )
+        .style(theme.warning)
         .alignment(Alignment::Center);
 
     let centered_area = Rect {
@@ -272,6 +724,7 @@ fn draw_empty_state(f: &mut Frame) {
 }
 
 fn draw_task_list(f: &mut Frame, app: &App, area: Rect, in_filter_mode: bool, in_param_mode: bool) {
+    let theme = &app.theme;
     if app.filtered_tasks.is_empty() {
         let message = if app.filter.is_empty() {
             "no tasks found"
@@ -283,10 +736,11 @@ fn draw_task_list(f: &mut Frame, app: &App, area: Rect, in_filter_mode: bool, in
             .block(
                 Block::default()
                     .title(" tasks ")
-                    .borders(Borders::ALL),
+                    .borders(Borders::ALL)
+                    .border_style(theme.border),
             )
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Yellow));
+            .style(theme.warning);
 
         f.render_widget(paragraph, area);
     } else {
@@ -298,29 +752,74 @@ fn draw_task_list(f: &mut Frame, app: &App, area: Rect, in_filter_mode: bool, in
                 let task = &app.tasks[task_idx];
                 let is_selected = idx == app.selected_index;
 
-                let mut content = vec![Span::styled(
-                    format!("  {}", task.name),
-                    if is_selected {
-                        Style::default()
-                            .fg(Color::Black)
-                            .bg(Color::Cyan)
-                            .add_modifier(Modifier::BOLD)
-                    } else {
-                        Style::default().fg(Color::Cyan)
-                    },
-                )];
+                if let Some(module) = app.collapsed_module(task) {
+                    return ListItem::new(Line::from(Span::styled(
+                        format!("▸ {}:: ({} recipes)", module, app.module_task_count(&module)),
+                        if is_selected {
+                            theme.selection.add_modifier(Modifier::BOLD)
+                        } else {
+                            theme.accent.add_modifier(Modifier::DIM)
+                        },
+                    )));
+                }
+
+                // running tasks are marked, background ones in a colour of their own
+                let mut marker = match app.find_active_run(&task.name) {
+                    Some(run) if run.background => Span::styled("● ", theme.background),
+                    Some(_) => Span::styled("● ", theme.running),
+                    None => Span::raw("  "),
+                };
+                if is_selected {
+                    marker = marker.patch_style(Style { fg: None, ..theme.selection });
+                }
+                let mut content = vec![
+                    marker,
+                    Span::styled(
+                        task.name.clone(),
+                        if is_selected {
+                            theme.selection.add_modifier(Modifier::BOLD)
+                        } else {
+                            theme.accent
+                        },
+                    ),
+                ];
+
+                if !task.parameters.is_empty() {
+                    let signature = task
+                        .parameters
+                        .iter()
+                        .map(|p| p.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    content.push(Span::styled(
+                        format!(" {signature}"),
+                        if is_selected {
+                            theme.selected(theme.params)
+                        } else {
+                            theme.params
+                        },
+                    ));
+                }
+
+                if let Some((_, group)) = app.config.group(&task.name) {
+                    content.push(Span::styled(
+                        format!(" [{group}]"),
+                        if is_selected {
+                            theme.selected(theme.group)
+                        } else {
+                            theme.group
+                        },
+                    ));
+                }
 
                 if !task.description.is_empty() {
                     content.push(Span::raw(" "));
                     content.push(Span::styled(
                         format!("({})", task.description),
                         if is_selected {
-                            Style::default()
-                                .fg(Color::Black)
-                                .bg(Color::Cyan)
-                                .add_modifier(Modifier::DIM)
+                            theme.selected(theme.description)
                         } else {
-                            Style::default().fg(Color::Gray).add_modifier(Modifier::DIM)
+                            theme.description
                         },
                     ));
                 }
@@ -328,14 +827,11 @@ fn draw_task_list(f: &mut Frame, app: &App, area: Rect, in_filter_mode: bool, in
                 if !task.dependencies.is_empty() {
                     content.push(Span::raw(" "));
                     content.push(Span::styled(
-                        format!("[deps: {}]", task.dependencies.join(", ")),
+                        format!("[deps: {}]", join_dependencies(&task.dependencies)),
                         if is_selected {
-                            Style::default()
-                                .fg(Color::Black)
-                                .bg(Color::Cyan)
-                                .add_modifier(Modifier::DIM)
+                            theme.selected(theme.deps)
                         } else {
-                            Style::default().fg(Color::Yellow).add_modifier(Modifier::DIM)
+                            theme.deps
                         },
                     ));
                 }
@@ -348,17 +844,25 @@ fn draw_task_list(f: &mut Frame, app: &App, area: Rect, in_filter_mode: bool, in
             .block(
                 Block::default()
                     .title(format!(
-                        " {} tasks ({} shown) ",
+                        " {} tasks ({} shown) · {}{}{} ",
                         if app.file_type == crate::parser::FileType::Makefile {
                             "makefile"
                         } else {
                             "justfile"
                         },
-                        app.filtered_count()
+                        app.filtered_count(),
+                        match (app.execution_mode, app.file_type) {
+                            (ExecutionMode::Inline, _) => "inline",
+                            (ExecutionMode::Delegate, crate::parser::FileType::Makefile) => "via make",
+                            (ExecutionMode::Delegate, crate::parser::FileType::Justfile) => "via just",
+                        },
+                        if app.use_pty { " · pty" } else { "" },
+                        if app.config_errors.is_empty() { "" } else { " · config errors, see ?" }
                     ))
-                    .borders(Borders::ALL),
+                    .borders(Borders::ALL)
+                    .border_style(theme.border),
             )
-            .style(Style::default().fg(Color::White));
+            .style(theme.text);
 
         f.render_widget(list, area);
     }
@@ -366,22 +870,22 @@ fn draw_task_list(f: &mut Frame, app: &App, area: Rect, in_filter_mode: bool, in
     let filter_text = if in_filter_mode {
         format!("Filter: {} _", app.filter)
     } else if in_param_mode {
-        format!("Params: {} _", app.param_input)
-    } else if app.filter.is_empty() && app.param_input.is_empty() {
-        "↑↓ Navigate | / Filter | p Params | Enter Run | g Graph | o Output | h History | ? Help | q Quit".to_string()
-    } else if !app.filter.is_empty() {
-        format!("Filter: {} (Esc to clear)", app.filter)
+        "Tab Next field | Shift+Tab Previous | Enter Run | Esc Cancel".to_string()
+    } else if !app.pending_keys.is_empty() {
+        format!("{} …", format_sequence(&app.pending_keys))
+    } else if app.filter.is_empty() {
+        key_hints(&app.keymap)
     } else {
-        format!("Params: {} (Esc to clear)", app.param_input)
+        format!("Filter: {} (Esc to clear)", app.filter)
     };
 
     let footer = Paragraph::new(filter_text)
         .style(if in_filter_mode {
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            theme.warning.add_modifier(Modifier::BOLD)
         } else if in_param_mode {
-            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+            theme.accent.add_modifier(Modifier::BOLD)
         } else {
-            Style::default().fg(Color::DarkGray)
+            theme.footer
         })
         .alignment(Alignment::Center);
 
@@ -395,12 +899,97 @@ fn draw_task_list(f: &mut Frame, app: &App, area: Rect, in_filter_mode: bool, in
     f.render_widget(footer, footer_area);
 }
 
-fn draw_output_panel(f: &mut Frame, app: &App, area: Rect) {
-    let output = Paragraph::new(app.current_output.as_str())
-        .block(Block::default().title(" output ").borders(Borders::ALL))
-        .style(Style::default().fg(Color::Green))
+fn draw_output_panel(f: &mut Frame, app: &mut App, mut area: Rect) {
+    // a running task that has succeeded before gets a progress estimate
+    // under its output
+    let progress = app
+        .viewed_run()
+        .filter(|run| run.is_active())
+        .and_then(|run| Some((run.elapsed(), run.estimate?)));
+    if let Some((elapsed, estimate)) = progress {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1)])
+            .split(area);
+        area = chunks[0];
+        f.render_widget(progress_gauge(elapsed, estimate, &app.theme), chunks[1]);
+    }
+
+    // estimate wrapped height so a running task's output can follow its tail
+    let inner_width = area.width.saturating_sub(2).max(1) as usize;
+    let inner_height = area.height.saturating_sub(2);
+    let theme = &app.theme;
+    let lines: Vec<Line> = app.visible_output().map(|shown| shown.line.clone()).collect();
+    let line_count: usize = lines
+        .iter()
+        .map(|line| line.width().max(1).div_ceil(inner_width))
+        .sum();
+    app.output_max_scroll = u16::try_from(line_count)
+        .unwrap_or(u16::MAX)
+        .saturating_sub(inner_height);
+    let scroll = match app.view_scroll() {
+        ScrollState { follow: true, .. } => app.output_max_scroll,
+        ScrollState { offset, .. } => offset.min(app.output_max_scroll),
+    };
+
+    // one tab per run, badged with its state
+    let mut title = vec![Span::raw(" output ")];
+    for (idx, run) in app.runs.iter().enumerate() {
+        let (badge, mut style) = match run.status {
+            None => ("●", theme.running),
+            Some(status) if status.is_success() => ("✓", theme.success),
+            Some(_) => ("✗", theme.failure),
+        };
+        if !app.showing_text && idx == app.selected_run {
+            style = style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+        }
+        title.push(Span::styled(format!(" {} {} ", badge, run.task_name), style));
+    }
+    if let Some(run) = app.viewed_run() {
+        let state = match run.status {
+            None if run.cancelling => "cancelling".to_string(),
+            None => "running".to_string(),
+            Some(status) => status.to_string(),
+        };
+        title.push(Span::raw(format!(" · {} ({:.1}s) ", state, run.elapsed().as_secs_f64())));
+        if run.dropped > 0 {
+            title.push(Span::styled(
+                format!(" · last {MAX_SHOWN_LINES} lines, full log in history "),
+                theme.muted,
+            ));
+        }
+    }
+    if app.stderr_only {
+        title.push(Span::raw(" · stderr only "));
+    }
+    let title = Line::from(title);
+
+    let output = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(theme.border),
+        )
+        .style(theme.output)
         .wrap(Wrap { trim: true })
-        .scroll((app.output_scroll, 0));
+        .scroll((scroll, 0));
 
     f.render_widget(output, area);
 }
+
+// elapsed time against how long the task usually takes
+fn progress_gauge(elapsed: Duration, estimate: Duration, theme: &Theme) -> Gauge<'static> {
+    let ratio = elapsed.as_secs_f64() / estimate.as_secs_f64().max(0.001);
+    let (label, style) = if elapsed < estimate {
+        let left = (estimate - elapsed).as_secs_f64();
+        (format!("~{:.1}s left of ~{:.1}s", left, estimate.as_secs_f64()), theme.accent)
+    } else {
+        let over = (elapsed - estimate).as_secs_f64();
+        (format!("{:.1}s over the usual {:.1}s", over, estimate.as_secs_f64()), theme.warning)
+    };
+    Gauge::default()
+        .gauge_style(Style { bg: theme.muted.fg, ..style })
+        .ratio(ratio.min(1.0))
+        .label(label)
+}